@@ -0,0 +1,63 @@
+// Copyright 2020 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A badge of the health of the examples, in the format of the
+//! [endpoint](https://shields.io/badges/endpoint-badge) badges of shields.io.
+
+use crate::json::Json;
+
+/// The version of the endpoint schema, which shields.io requires to be `1`.
+const SCHEMA_VERSION: i64 = 1;
+
+/// The badge telling how many examples work, with a page, and how many are skipped.
+pub fn badge_json(working: usize, skipped: usize) -> Json {
+    let message = if skipped == 0 {
+        format!("{} working", working)
+    } else {
+        format!("{} working, {} skipped", working, skipped)
+    };
+    let color = if working == 0 {
+        "red"
+    } else if skipped == 0 {
+        "brightgreen"
+    } else if working > skipped {
+        "yellow"
+    } else {
+        "red"
+    };
+    Json::object(vec![
+        ("schemaVersion", Json::Number(SCHEMA_VERSION)),
+        ("label", "examples".into()),
+        ("message", message.into()),
+        ("color", color.into()),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors_the_badge_by_health() {
+        assert_eq!(
+            badge_json(24, 3).to_string(),
+            r#"{"schemaVersion":1,"label":"examples","message":"24 working, 3 skipped","color":"yellow"}"#
+        );
+        assert_eq!(
+            badge_json(5, 0).to_string(),
+            r#"{"schemaVersion":1,"label":"examples","message":"5 working","color":"brightgreen"}"#
+        );
+        assert!(badge_json(1, 1).to_string().contains(r#""color":"red""#));
+    }
+}
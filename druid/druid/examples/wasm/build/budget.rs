@@ -0,0 +1,145 @@
+// Copyright 2020 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Size budgets of the pages and the wasm bundle, to catch bloat in CI.
+//!
+//! All examples share a single bundle, so the size of an example is what its page loads: the page
+//! itself and the bundle. The total size is that of the bundle and all pages. Sizes are only
+//! checked against the budgets which are configured.
+
+use std::fs;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::discover::Example;
+use crate::json::Json;
+
+/// The files of the bundle loaded by every page.
+const BUNDLE: &[&str] = &["druid_wasm_examples_bg.wasm", "druid_wasm_examples.js"];
+
+/// The measured sizes, in bytes.
+pub struct Sizes<'a> {
+    pub bundle: u64,
+    /// The size of the page of each example.
+    pub pages: Vec<(&'a Example, u64)>,
+}
+
+impl Sizes<'_> {
+    pub fn total(&self) -> u64 {
+        self.bundle + self.pages.iter().map(|(_, size)| size).sum::<u64>()
+    }
+}
+
+/// Parse a size in bytes, optionally with a `k` or `M` suffix for kibibytes or mebibytes.
+pub fn parse_size(size: &str) -> Result<u64, String> {
+    let size = size.trim();
+    let (digits, unit) = match size.char_indices().last() {
+        Some((i, 'k')) | Some((i, 'K')) => (&size[..i], 1024),
+        Some((i, 'M')) => (&size[..i], 1024 * 1024),
+        _ => (size, 1),
+    };
+    digits
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(unit))
+        .ok_or_else(|| format!("`{}` is not a size, e.g. `1500000`, `300k` or `2M`", size))
+}
+
+/// Measure the bundle in `pkg_dir` and the pages of `examples` in `html_dir`. `None` if the bundle
+/// hasn't been built yet.
+pub fn measure<'a>(
+    examples: &'a [Example],
+    pkg_dir: &Path,
+    html_dir: &Path,
+    ext: &str,
+) -> Option<Sizes<'a>> {
+    let mut bundle = 0;
+    for file in BUNDLE {
+        bundle += fs::metadata(pkg_dir.join(file)).ok()?.len();
+    }
+    let pages = examples
+        .iter()
+        .map(|example| {
+            let page = html_dir.join(format!("{}.{}", example.page, ext));
+            (example, fs::metadata(page).map_or(0, |m| m.len()))
+        })
+        .collect();
+    Some(Sizes { bundle, pages })
+}
+
+/// Whether any budget is configured, for the whole build or an example.
+pub fn any_budget(examples: &[Example], config: &Config) -> bool {
+    config.size_budget.is_some()
+        || config.example_size_budget.is_some()
+        || examples.iter().any(|e| e.meta.size_budget.is_some())
+}
+
+/// Check `sizes` against the budgets, describing each one exceeded.
+pub fn check(sizes: &Sizes, config: &Config) -> Vec<String> {
+    let mut exceeded = Vec::new();
+    for (example, page) in &sizes.pages {
+        let budget = example.meta.size_budget.or(config.example_size_budget);
+        if let Some(budget) = budget {
+            let size = sizes.bundle + page;
+            if size > budget {
+                exceeded.push(format!(
+                    "`{}` loads {} bytes, over its budget of {} bytes",
+                    example.module_path(),
+                    size,
+                    budget
+                ));
+            }
+        }
+    }
+    if let Some(budget) = config.size_budget {
+        if sizes.total() > budget {
+            exceeded.push(format!(
+                "the bundle and pages take {} bytes, over the budget of {} bytes",
+                sizes.total(),
+                budget
+            ));
+        }
+    }
+    exceeded
+}
+
+/// The sizes for the build report.
+pub fn json(sizes: &Sizes) -> Json {
+    let bytes = |size: u64| Json::Number(size as i64);
+    let pages = sizes
+        .pages
+        .iter()
+        .map(|(example, size)| (example.page.as_str(), bytes(*size)));
+    Json::object(vec![
+        ("bundle", bytes(sizes.bundle)),
+        ("total", bytes(sizes.total())),
+        ("pages", Json::object(pages)),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("1500000"), Ok(1_500_000));
+        assert_eq!(parse_size("300k"), Ok(300 * 1024));
+        assert_eq!(parse_size(" 2M "), Ok(2 * 1024 * 1024));
+        assert!(parse_size("2G").is_err());
+        assert!(parse_size("k").is_err());
+        assert!(parse_size("-1").is_err());
+    }
+}
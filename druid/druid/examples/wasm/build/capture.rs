@@ -0,0 +1,134 @@
+// Copyright 2020 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Screenshots of the examples, taken by an external tool.
+//!
+//! The build doesn't take screenshots itself. It lists the examples whose captured screenshot is
+//! missing or older than their source in `captures.json`, for a tool driving a headless browser to
+//! take them. See the README for the format of the list.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config::Config;
+use crate::discover::Example;
+use crate::json::Json;
+
+/// The version of the format of the list, incremented on incompatible changes.
+const FORMAT_VERSION: i64 = 1;
+
+/// An example needing a screenshot.
+pub struct Capture<'a> {
+    pub example: &'a Example,
+    /// Where the tool writes the screenshot.
+    pub screenshot: PathBuf,
+    /// Whether the screenshot is `missing` or `stale`.
+    pub reason: &'static str,
+}
+
+/// The path the captured screenshot of `example` is written to: in the assets directory, named
+/// after its page, or else the `foo.png` next to its source, which is its screenshot by default.
+pub fn target(example: &Example, config: &Config) -> PathBuf {
+    match &config.assets_dir {
+        Some(dir) => dir.join(format!("{}.png", example.page)),
+        None => example.path.with_extension("png"),
+    }
+}
+
+/// The examples needing a screenshot, in the order of `examples`. Examples with a screenshot of
+/// their own, other than their captured one, are left alone.
+pub fn needed<'a>(examples: &'a [Example], config: &Config) -> Vec<Capture<'a>> {
+    let mut captures = Vec::new();
+    for example in examples {
+        let screenshot = target(example, config);
+        if matches!(&example.meta.screenshot, Some(own) if *own != screenshot) {
+            continue;
+        }
+        let reason = match fs::metadata(&screenshot).and_then(|m| m.modified()) {
+            Err(_) => "missing",
+            Ok(taken) if taken < example.modified => "stale",
+            Ok(_) => continue,
+        };
+        captures.push(Capture {
+            example,
+            screenshot,
+            reason,
+        });
+    }
+    captures
+}
+
+/// The list of `captures` handed to the capture tool. Pages are given relative to `crate_dir`,
+/// which has to be served for them to load their bundle.
+pub fn captures_json(
+    captures: &[Capture],
+    crate_dir: &Path,
+    site_dir: &Path,
+    config: &Config,
+) -> Json {
+    let path = |p: &Path| Json::String(p.display().to_string());
+    let site: String = site_dir
+        .strip_prefix(crate_dir)
+        .unwrap()
+        .iter()
+        .map(|segment| format!("{}/", segment.to_string_lossy()))
+        .collect();
+    let captures = captures
+        .iter()
+        .map(|capture| {
+            let example = capture.example;
+            Json::object(vec![
+                ("module", example.module_path().into()),
+                (
+                    "url",
+                    format!("{}{}", site, config.page_url(&example.page)).into(),
+                ),
+                ("entry", example.entry.as_str().into()),
+                ("screenshot", path(&capture.screenshot)),
+                ("reason", capture.reason.into()),
+            ])
+        })
+        .collect();
+
+    Json::object(vec![
+        ("version", Json::Number(FORMAT_VERSION)),
+        ("root", path(crate_dir)),
+        ("captures", Json::Array(captures)),
+    ])
+}
+
+/// Run the capture `tool` with the path of the `list` as the only argument.
+///
+/// Panics if the tool can't be run or fails.
+pub fn run(tool: &Path, list: &Path, crate_dir: &Path) {
+    let status = Command::new(tool)
+        .arg(list)
+        .current_dir(crate_dir)
+        .status()
+        .unwrap_or_else(|e| {
+            panic!(
+                "RCCHAT_CAPTURE_TOOL: failed to run {}: {}",
+                tool.display(),
+                e
+            )
+        });
+    if !status.success() {
+        panic!(
+            "RCCHAT_CAPTURE_TOOL: {} failed with {}",
+            tool.display(),
+            status
+        );
+    }
+}
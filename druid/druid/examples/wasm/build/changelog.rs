@@ -0,0 +1,100 @@
+// Copyright 2020 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The changelog of the examples added and removed between builds.
+//!
+//! The examples of each build are recorded in the ledger of the generated files, see
+//! [`Output`](crate::output::Output), which is what the current examples are compared against.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{ErrorKind, Result};
+use std::path::Path;
+
+const HEADER: &str = "# Examples changelog\n";
+
+/// Append the examples added and removed since the `previous` build to the changelog at `path`,
+/// under a section for `version`. Nothing is written if the examples didn't change.
+pub fn update(
+    path: &Path,
+    version: &str,
+    previous: &BTreeSet<String>,
+    current: &BTreeSet<String>,
+) -> Result<()> {
+    let added: Vec<&String> = current.difference(previous).collect();
+    let removed: Vec<&String> = previous.difference(current).collect();
+    if added.is_empty() && removed.is_empty() {
+        return Ok(());
+    }
+
+    let mut log = match fs::read_to_string(path) {
+        Ok(log) => log,
+        Err(e) if e.kind() == ErrorKind::NotFound => HEADER.to_string(),
+        Err(e) => return Err(e),
+    };
+    // Builds of the same version share a section, which is always the last one.
+    let heading = format!("## {}", version);
+    if log.lines().rev().find(|line| line.starts_with("## ")) != Some(heading.as_str()) {
+        log.push_str(&format!("\n{}\n\n", heading));
+    }
+    for example in added {
+        log.push_str(&format!("- Added `{}`\n", example));
+    }
+    for example in removed {
+        log.push_str(&format!("- Removed `{}`\n", example));
+    }
+    crate::output::replace(path, log)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn records_changes_once() {
+        let dir = crate::fixture_dir("changelog");
+        let path = dir.join("examples_changelog.md");
+        update(
+            &path,
+            "0.1.0",
+            &set(&["calc", "svg"]),
+            &set(&["calc", "flex"]),
+        )
+        .unwrap();
+        update(
+            &path,
+            "0.1.0",
+            &set(&["calc", "flex"]),
+            &set(&["calc", "flex"]),
+        )
+        .unwrap();
+        update(&path, "0.1.0", &set(&["calc", "flex"]), &set(&["flex"])).unwrap();
+        update(&path, "0.2.0", &set(&["flex"]), &set(&["flex", "list"])).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# Examples changelog\n\
+             \n## 0.1.0\n\n\
+             - Added `flex`\n\
+             - Removed `svg`\n\
+             - Removed `calc`\n\
+             \n## 0.2.0\n\n\
+             - Added `list`\n"
+        );
+    }
+}
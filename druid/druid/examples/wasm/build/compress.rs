@@ -0,0 +1,380 @@
+// Copyright 2020 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A small gzip encoder, to precompress generated pages without pulling in dependencies.
+//!
+//! It uses a single deflate block with the fixed Huffman codes and a simple LZ77 matcher, which
+//! compresses html well enough for static hosting.
+
+/// Base match lengths of the length codes 257 to 285, with their number of extra bits.
+const LENGTHS: [(u16, u8); 29] = [
+    (3, 0),
+    (4, 0),
+    (5, 0),
+    (6, 0),
+    (7, 0),
+    (8, 0),
+    (9, 0),
+    (10, 0),
+    (11, 1),
+    (13, 1),
+    (15, 1),
+    (17, 1),
+    (19, 2),
+    (23, 2),
+    (27, 2),
+    (31, 2),
+    (35, 3),
+    (43, 3),
+    (51, 3),
+    (59, 3),
+    (67, 4),
+    (83, 4),
+    (99, 4),
+    (115, 4),
+    (131, 5),
+    (163, 5),
+    (195, 5),
+    (227, 5),
+    (258, 0),
+];
+
+/// Base distances of the distance codes 0 to 29, with their number of extra bits.
+const DISTANCES: [(u16, u8); 30] = [
+    (1, 0),
+    (2, 0),
+    (3, 0),
+    (4, 0),
+    (5, 1),
+    (7, 1),
+    (9, 2),
+    (13, 2),
+    (17, 3),
+    (25, 3),
+    (33, 4),
+    (49, 4),
+    (65, 5),
+    (97, 5),
+    (129, 6),
+    (193, 6),
+    (257, 7),
+    (385, 7),
+    (513, 8),
+    (769, 8),
+    (1025, 9),
+    (1537, 9),
+    (2049, 10),
+    (3073, 10),
+    (4097, 11),
+    (6145, 11),
+    (8193, 12),
+    (12289, 12),
+    (16385, 13),
+    (24577, 13),
+];
+
+const WINDOW: usize = 32 * 1024;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+/// How many earlier positions with the same hash to try, trading speed for compression.
+const MAX_CHAIN: usize = 64;
+const HASH_BITS: u32 = 15;
+
+/// Compress `data` into the gzip format.
+///
+/// The header carries no file name and a zero modification time, so the output only depends on
+/// `data`.
+pub fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+    out.extend(deflate(data));
+    out.extend(&crc32(data).to_le_bytes());
+    out.extend(&(data.len() as u32).to_le_bytes());
+    out
+}
+
+/// Writes bits least significant first, as deflate requires.
+struct BitWriter {
+    bytes: Vec<u8>,
+    acc: u32,
+    len: u8,
+}
+
+impl BitWriter {
+    fn bits(&mut self, value: u32, count: u8) {
+        for i in 0..count {
+            self.acc |= ((value >> i) & 1) << self.len;
+            self.len += 1;
+            if self.len == 8 {
+                self.bytes.push(self.acc as u8);
+                self.acc = 0;
+                self.len = 0;
+            }
+        }
+    }
+
+    /// Huffman codes are packed starting with their most significant bit.
+    fn code(&mut self, code: u32, count: u8) {
+        for i in (0..count).rev() {
+            self.bits((code >> i) & 1, 1);
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.len > 0 {
+            self.bytes.push(self.acc as u8);
+        }
+        self.bytes
+    }
+}
+
+/// Write a literal/length symbol with the fixed Huffman code.
+fn symbol(w: &mut BitWriter, symbol: u16) {
+    let symbol = u32::from(symbol);
+    match symbol {
+        0..=143 => w.code(0x30 + symbol, 8),
+        144..=255 => w.code(0x190 + symbol - 144, 9),
+        256..=279 => w.code(symbol - 256, 7),
+        _ => w.code(0xc0 + symbol - 280, 8),
+    }
+}
+
+/// Find the code of `value` in a table of base values, returning its index and base.
+fn lookup(table: &[(u16, u8)], value: usize) -> (usize, u16, u8) {
+    let index = table
+        .iter()
+        .rposition(|(base, _)| usize::from(*base) <= value)
+        .unwrap();
+    (index, table[index].0, table[index].1)
+}
+
+fn hash(data: &[u8], i: usize) -> usize {
+    let v = u32::from(data[i]) | u32::from(data[i + 1]) << 8 | u32::from(data[i + 2]) << 16;
+    (v.wrapping_mul(0x9e37_79b1) >> (32 - HASH_BITS)) as usize
+}
+
+/// Compress `data` into a raw deflate stream, as used inside gzip and zip files.
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut w = BitWriter {
+        bytes: Vec::with_capacity(data.len() / 2),
+        acc: 0,
+        len: 0,
+    };
+    // A single, final block using the fixed Huffman codes.
+    w.bits(1, 1);
+    w.bits(1, 2);
+
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut prev = vec![usize::MAX; data.len()];
+    let insert = |head: &mut Vec<usize>, prev: &mut Vec<usize>, i: usize| {
+        if i + MIN_MATCH <= data.len() {
+            let h = hash(data, i);
+            prev[i] = head[h];
+            head[h] = i;
+        }
+    };
+
+    let mut i = 0;
+    while i < data.len() {
+        let mut best = (0, 0);
+        if i + MIN_MATCH <= data.len() {
+            let mut candidate = head[hash(data, i)];
+            let max = (data.len() - i).min(MAX_MATCH);
+            for _ in 0..MAX_CHAIN {
+                if candidate == usize::MAX || i - candidate > WINDOW {
+                    break;
+                }
+                let len = (0..max)
+                    .take_while(|&k| data[candidate + k] == data[i + k])
+                    .count();
+                if len > best.0 {
+                    best = (len, i - candidate);
+                    if len == max {
+                        break;
+                    }
+                }
+                candidate = prev[candidate];
+            }
+        }
+
+        let (len, distance) = best;
+        if len >= MIN_MATCH {
+            let (code, base, extra) = lookup(&LENGTHS, len);
+            symbol(&mut w, 257 + code as u16);
+            w.bits((len - usize::from(base)) as u32, extra);
+            let (code, base, extra) = lookup(&DISTANCES, distance);
+            w.code(code as u32, 5);
+            w.bits((distance - usize::from(base)) as u32, extra);
+            for k in i..i + len {
+                insert(&mut head, &mut prev, k);
+            }
+            i += len;
+        } else {
+            symbol(&mut w, u16::from(data[i]));
+            insert(&mut head, &mut prev, i);
+            i += 1;
+        }
+    }
+
+    // End of block.
+    symbol(&mut w, 256);
+    w.finish()
+}
+
+/// The CRC-32 checksum used by gzip.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut table = [0u32; 256];
+    for (n, entry) in table.iter_mut().enumerate() {
+        let mut c = n as u32;
+        for _ in 0..8 {
+            c = if c & 1 != 0 {
+                0xedb8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+        }
+        *entry = c;
+    }
+    !data.iter().fold(!0u32, |crc, b| {
+        table[((crc ^ u32::from(*b)) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// Reads bits least significant first, undoing `BitWriter`.
+    struct BitReader<'a> {
+        bytes: &'a [u8],
+        pos: usize,
+    }
+
+    impl BitReader<'_> {
+        fn bits(&mut self, count: u8) -> u32 {
+            let mut value = 0;
+            for i in 0..count {
+                let bit = (self.bytes[self.pos / 8] >> (self.pos % 8)) & 1;
+                value |= u32::from(bit) << i;
+                self.pos += 1;
+            }
+            value
+        }
+
+        fn code(&mut self, count: u8) -> u32 {
+            (0..count).fold(0, |code, _| code << 1 | self.bits(1))
+        }
+    }
+
+    /// Decode a literal/length symbol of the fixed Huffman code.
+    fn read_symbol(r: &mut BitReader) -> u16 {
+        let code = r.code(7);
+        if code <= 0x17 {
+            return 256 + code as u16;
+        }
+        let code = code << 1 | r.bits(1);
+        match code {
+            0x30..=0xbf => (code - 0x30) as u16,
+            0xc0..=0xc7 => (280 + code - 0xc0) as u16,
+            _ => (144 + (code << 1 | r.bits(1)) - 0x190) as u16,
+        }
+    }
+
+    /// A minimal inflater for the fixed Huffman blocks written by `deflate`.
+    pub fn inflate(stream: &[u8]) -> Vec<u8> {
+        let mut r = BitReader {
+            bytes: stream,
+            pos: 0,
+        };
+        let mut out = Vec::new();
+        loop {
+            let last = r.bits(1);
+            assert_eq!(r.bits(2), 1, "only fixed Huffman blocks are written");
+            loop {
+                let symbol = read_symbol(&mut r);
+                match symbol {
+                    0..=255 => out.push(symbol as u8),
+                    256 => break,
+                    _ => {
+                        let (base, extra) = LENGTHS[usize::from(symbol - 257)];
+                        let len = usize::from(base) + r.bits(extra) as usize;
+                        let (base, extra) = DISTANCES[r.code(5) as usize];
+                        let distance = usize::from(base) + r.bits(extra) as usize;
+                        let start = out.len() - distance;
+                        for k in 0..len {
+                            out.push(out[start + k]);
+                        }
+                    }
+                }
+            }
+            if last == 1 {
+                return out;
+            }
+        }
+    }
+
+    /// Text with long repeats, followed by bytes without any, to exercise both kinds of symbols
+    /// and matches reaching across the whole window.
+    fn sample(len: usize) -> Vec<u8> {
+        let text = b"<li><a href=\"./html/hello.html\">hello</a></li>\n";
+        let mut state = 0x2545_f491_u32;
+        (0..len)
+            .map(|i| {
+                if i % 5000 < 4000 {
+                    text[i % text.len()]
+                } else {
+                    state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                    (state >> 16) as u8
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn computes_known_checksums() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"a"), 0xe8b7_be43);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(
+            crc32(b"The quick brown fox jumps over the lazy dog"),
+            0x414f_a339
+        );
+    }
+
+    #[test]
+    fn inflates_back_to_the_input() {
+        for data in [
+            Vec::new(),
+            b"a".to_vec(),
+            b"aaaaaaaaaaaaaaaaaaaa".to_vec(),
+            sample(100_000),
+        ] {
+            let stream = deflate(&data);
+            assert_eq!(inflate(&stream), data);
+        }
+        // Repeats compress.
+        assert!(deflate(&sample(100_000)).len() < 40_000);
+    }
+
+    #[test]
+    fn wraps_the_stream_in_gzip() {
+        for data in [Vec::new(), sample(70_000)] {
+            let gz = gzip(&data);
+            assert_eq!(gz[..10], [0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff]);
+            let (stream, trailer) = gz[10..].split_at(gz.len() - 18);
+            assert_eq!(inflate(stream), data);
+            assert_eq!(trailer[..4], crc32(&data).to_le_bytes());
+            assert_eq!(trailer[4..], (data.len() as u32).to_le_bytes());
+        }
+    }
+}
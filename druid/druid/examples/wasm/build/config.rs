@@ -0,0 +1,937 @@
+// Copyright 2020 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Build configuration, read from `RCCHAT_*` environment variables.
+
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{env, fs, thread};
+
+use crate::discover::{root_name, SortMode, MIN_PAGE_NAME};
+use crate::html::{Markup, ScriptMode};
+use crate::info::BuildInfo;
+use crate::manifest::Icon;
+use crate::server::Server;
+
+/// An additional directory of examples to include.
+pub struct ExtraRoot {
+    /// The module name the examples are declared in.
+    pub name: String,
+    /// The source directory of the examples.
+    pub path: PathBuf,
+}
+
+pub struct Config {
+    /// The directory of the example sources, from `RCCHAT_EXAMPLES_SRC`. Defaults to the parent
+    /// of this crate.
+    pub examples_src: PathBuf,
+    /// Additional example directories, from `RCCHAT_EXTRA_EXAMPLES`.
+    pub extra_roots: Vec<ExtraRoot>,
+    /// The markup pages are generated in, XHTML if `RCCHAT_XHTML` is set.
+    pub markup: Markup,
+    /// How the pages load the bundle, from `RCCHAT_SCRIPT_MODE`.
+    pub script_mode: ScriptMode,
+    /// The content security policy declared by the pages, from `RCCHAT_CSP_POLICY` if
+    /// `RCCHAT_CSP` is set. `{hashes}` stands for the hashes of the inline scripts of a page.
+    pub csp: Option<String>,
+    /// The caching policy declared by the pages, from `RCCHAT_CACHE_POLICY` if
+    /// `RCCHAT_CACHE_CONTROL` is set.
+    pub cache_control: Option<String>,
+    /// Whether to show the source of each example below it, from `RCCHAT_EMBED_SOURCE`.
+    pub embed_source: bool,
+    /// Whether to show a navigation bar at the top of the example pages, from `RCCHAT_NAV_BAR`.
+    pub nav_bar: bool,
+    /// JavaScript run before each example is initialized, from the file at `RCCHAT_BEFORE_INIT`.
+    pub before_init: Option<String>,
+    /// JavaScript run after each example has been started, from the file at `RCCHAT_AFTER_INIT`.
+    pub after_init: Option<String>,
+    /// The order of the examples on the index, from `RCCHAT_SORT`.
+    pub sort: SortMode,
+    /// A consent or analytics banner added to every page of release builds, from the file at
+    /// `RCCHAT_BANNER`.
+    pub banner: Option<String>,
+    /// The page of the only example to regenerate, from `RCCHAT_ONLY_REGEN`.
+    pub only_regen: Option<String>,
+    /// The page of the example embedded at the top of the index, from `RCCHAT_LANDING_EXAMPLE`.
+    pub landing: Option<String>,
+    /// The character set declared by pages, from `RCCHAT_CHARSET`.
+    pub charset: String,
+    /// The language of the pages, from `RCCHAT_LANG`.
+    pub lang: String,
+    /// The language tags and site urls of the localized deployments of the examples, including
+    /// this one, from `RCCHAT_LOCALES`. Empty without a base url.
+    pub locales: Vec<(String, String)>,
+    /// Whether to write gzip compressed copies of the pages, from `RCCHAT_GZIP`.
+    pub gzip: bool,
+    /// A program run with the discovered examples, from `RCCHAT_HOOK`.
+    pub hook: Option<PathBuf>,
+    /// The web server to write a configuration snippet for, from `RCCHAT_SERVER_CONFIG`.
+    pub server: Option<Server>,
+    /// The url the pages are published at, without a trailing slash, from `RCCHAT_BASE_URL`. With
+    /// pages per profile, this is the url of the directory of the profile.
+    pub base_url: Option<String>,
+    /// Whether to describe the examples as JSON-LD structured data on the index, from
+    /// `RCCHAT_JSON_LD`.
+    pub json_ld: bool,
+    /// Whether to show a QR code linking to each example on its page, from `RCCHAT_QR_CODES`. Only
+    /// set when the base url is known.
+    pub qr_codes: bool,
+    /// Whether to link each example page to its absolute url as the canonical one, from
+    /// `RCCHAT_CANONICAL`. Only set when the base url is known.
+    pub canonical: bool,
+    /// Files which are never deleted or overwritten, from the `.keep` file of this crate.
+    pub keep: Vec<PathBuf>,
+    /// The url of the directory of the wasm bundle imported by the pages, without a trailing
+    /// slash, from `RCCHAT_PKG_BASE`. Defaults to the `pkg` directory of this crate. With pages
+    /// per profile, the bundle of the profile is in a directory named after it.
+    pub pkg_base: String,
+    /// How many pages to render in parallel, from `RCCHAT_JOBS`.
+    pub jobs: usize,
+    /// Whether to write an inventory of the examples to `examples.csv`, from `RCCHAT_CSV`.
+    pub csv: bool,
+    /// Whether to write the spec of a headless browser test of the pages to `test_spec.json`,
+    /// from `RCCHAT_TEST_SPEC`.
+    pub test_spec: bool,
+    /// Whether to write the APIs of druid each example imports to `dependencies.json`, from
+    /// `RCCHAT_DEPENDENCIES`.
+    pub dependencies: bool,
+    /// Whether to write a shields.io badge with the number of examples to `badge.json`, from
+    /// `RCCHAT_BADGE`.
+    pub badge: bool,
+    /// Whether the generated `examples.in` is committed, from `RCCHAT_VENDORED`.
+    pub vendored: bool,
+    /// Whether to describe the examples by their doc comments, from `RCCHAT_DOC_DESCRIPTIONS`.
+    pub doc_descriptions: bool,
+    /// Whether to document the modules in `examples.in` with the descriptions of the examples,
+    /// from `RCCHAT_MODULE_DOCS`.
+    pub module_docs: bool,
+    /// Whether `examples.in` declares the modules of the examples without wrapping them in an
+    /// `examples` module, from `RCCHAT_FLAT_MODULES`.
+    pub flat_modules: bool,
+    /// Whether the modules in `examples.in` are only compiled for WASM targets, from
+    /// `RCCHAT_WASM_ONLY`.
+    pub wasm_only: bool,
+    /// The visibility the modules in `examples.in` are declared with, including a trailing space
+    /// unless they are private, from `RCCHAT_MODULE_VISIBILITY`.
+    pub module_visibility: &'static str,
+    /// One level of indentation in `examples.in`, from `RCCHAT_INDENT`.
+    pub indent: String,
+    /// Whether to write a feed of the examples for galleries loading them on demand, from
+    /// `RCCHAT_GALLERY_FEED`.
+    pub gallery_feed: bool,
+    /// Whether to write the SHA-256 checksums of the generated files, from `RCCHAT_CHECKSUMS`.
+    pub checksums: bool,
+    /// Whether to package the generated site in `site.zip`, from `RCCHAT_SITE_ZIP`.
+    pub site_zip: bool,
+    /// Whether to add the local bundle to `site.zip`, from `RCCHAT_SITE_ZIP_PKG`.
+    pub site_zip_pkg: bool,
+    /// Whether to write a static gallery of screenshots, from `RCCHAT_GALLERY`.
+    pub gallery: bool,
+    /// Whether to show a thumbnail of each example on the index, from `RCCHAT_THUMBNAILS`.
+    pub thumbnails: bool,
+    /// Whether to record added and removed examples in `examples_changelog.md`, from
+    /// `RCCHAT_CHANGELOG`.
+    pub changelog: bool,
+    /// Whether to write a service worker caching the pages for offline use, from
+    /// `RCCHAT_SERVICE_WORKER`.
+    pub service_worker: bool,
+    /// The directory screenshots are resolved against, from `RCCHAT_ASSETS_DIR`. Screenshots are
+    /// resolved against the directory of their example if unset.
+    pub assets_dir: Option<PathBuf>,
+    /// Whether to fail on problems which are otherwise only warned about, from `RCCHAT_STRICT`.
+    pub strict: bool,
+    /// Whether pages link to each other, the stylesheet and the bundle by absolute urls under
+    /// `base_url`, from `RCCHAT_ABSOLUTE_URLS`.
+    pub absolute_urls: bool,
+    /// The color mobile browsers tint their interface with, for pages without their own, from
+    /// `RCCHAT_THEME_COLOR`.
+    pub theme_color: Option<String>,
+    /// How many examples each page of the index lists, from `RCCHAT_INDEX_PAGE_SIZE`. The index
+    /// is a single page if unset.
+    pub index_page_size: Option<usize>,
+    /// The version, commit and date of the build shown at the bottom of the index, from
+    /// `RCCHAT_BUILD_INFO`.
+    pub build_info: Option<BuildInfo>,
+    /// What joins the module of an additional root and the name of an example in the names of
+    /// their pages and entry functions, from `RCCHAT_NAME_SEPARATOR`.
+    pub separator: String,
+    /// Whether to write how long each phase of the build took to `build_report.json`, from
+    /// `RCCHAT_BUILD_REPORT`.
+    pub build_report: bool,
+    /// Whether to write a web app manifest and link it from the pages, from `RCCHAT_MANIFEST`.
+    pub manifest: bool,
+    /// The icons of the app, from `RCCHAT_ICONS`. Missing and invalid icons are left out.
+    pub icons: Vec<Icon>,
+    /// The user and group id which created links and generated files are given to, from
+    /// `RCCHAT_OWNER`.
+    pub owner: Option<(u32, u32)>,
+    /// Whether example pages only load and run their example once asked to with a click, from
+    /// `RCCHAT_LAZY_INIT`.
+    pub lazy_init: bool,
+    /// The contents of `robots.txt`, from `RCCHAT_ROBOTS`.
+    pub robots: Option<String>,
+    /// Whether to write a `.nojekyll` file turning off Jekyll on GitHub Pages, from
+    /// `RCCHAT_GHPAGES`.
+    pub ghpages: bool,
+    /// Whether to skip the examples requiring features which aren't enabled, from
+    /// `RCCHAT_FEATURE_GATE`.
+    pub feature_gate: bool,
+    /// The command running an example locally shown on its page, with `{name}` standing for the
+    /// name of the example, from `RCCHAT_RUN_COMMAND` if `RCCHAT_RUN_INSTRUCTIONS` is set.
+    pub run_command: Option<String>,
+    /// The message replacing the canvas in browsers without WebAssembly, from
+    /// `RCCHAT_NO_WASM_MESSAGE`.
+    pub no_wasm_message: String,
+    /// The directory the pages of this build are generated in, named after its profile, if
+    /// `RCCHAT_PROFILE_PAGES` is set.
+    pub profile_dir: Option<String>,
+    /// Whether the example pages are written next to the index instead of into `html/`, from
+    /// `RCCHAT_FLAT_PAGES`.
+    pub flat_pages: bool,
+    /// Whether the index credits the authors of the examples too, from `RCCHAT_INDEX_AUTHORS`.
+    pub index_authors: bool,
+    /// Whether the index groups the examples in a collapsible section per category, and whether
+    /// the sections start expanded, from `RCCHAT_INDEX_CATEGORIES`.
+    pub category_sections: Option<bool>,
+    /// Whether to list the screenshots to capture in `captures.json`, from `RCCHAT_CAPTURE`.
+    pub capture: bool,
+    /// The tool run to capture the listed screenshots, from `RCCHAT_CAPTURE_TOOL`.
+    pub capture_tool: Option<PathBuf>,
+    /// The most bytes the bundle and all pages may take, from `RCCHAT_SIZE_BUDGET`.
+    pub size_budget: Option<u64>,
+    /// The most bytes the page of an example may load, along with the bundle, from
+    /// `RCCHAT_EXAMPLE_SIZE_BUDGET`.
+    pub example_size_budget: Option<u64>,
+    /// Whether example pages hint the browser to fetch the bundle early, from `RCCHAT_PRELOAD`.
+    pub preload: bool,
+    /// Whether to write the metadata of each example next to its page, from
+    /// `RCCHAT_EXAMPLE_JSON`.
+    pub example_json: bool,
+    /// Whether to check that the relative links of the pages lead to existing files, from
+    /// `RCCHAT_CHECK_LINKS`.
+    pub check_links: bool,
+    /// The most characters the page names of the examples may have before they are shortened,
+    /// from `RCCHAT_MAX_PAGE_NAME`.
+    pub max_page_name: usize,
+    /// How long to wait for `mklink` when linking directories on Windows, from
+    /// `RCCHAT_LINK_TIMEOUT`.
+    pub link_timeout: Duration,
+    /// Whether the examples directories are already in place instead of linked by the build, from
+    /// `RCCHAT_SKIP_LINK`.
+    pub skip_link: bool,
+}
+
+impl Config {
+    /// Read the configuration from the environment. Relative paths are resolved against
+    /// `crate_dir`.
+    pub fn from_env(crate_dir: &Path) -> Config {
+        let extra_roots = var("RCCHAT_EXTRA_EXAMPLES")
+            .map(|v| parse_extra_roots(&v, crate_dir))
+            .unwrap_or_default();
+
+        let profile_dir = flag("RCCHAT_PROFILE_PAGES").then(|| env::var("PROFILE").unwrap());
+        let lang = lang();
+        let locales = locales(
+            var("RCCHAT_LOCALES").as_deref(),
+            &lang,
+            base_url().as_deref(),
+            profile_dir.as_deref(),
+        );
+        let base_url = base_url().map(|url| match &profile_dir {
+            Some(profile) => format!("{}/{}", url, profile),
+            None => url,
+        });
+        let absolute_urls = flag("RCCHAT_ABSOLUTE_URLS");
+        if absolute_urls && base_url.is_none() {
+            panic!("RCCHAT_ABSOLUTE_URLS requires RCCHAT_BASE_URL to be set");
+        }
+        let qr_codes = flag("RCCHAT_QR_CODES") && {
+            if base_url.is_none() {
+                crate::warn("RCCHAT_QR_CODES needs RCCHAT_BASE_URL to be set, skipping it");
+            }
+            base_url.is_some()
+        };
+        let canonical = flag("RCCHAT_CANONICAL") && {
+            if base_url.is_none() {
+                crate::warn("RCCHAT_CANONICAL needs RCCHAT_BASE_URL to be set, skipping it");
+            }
+            base_url.is_some()
+        };
+
+        let strict = flag("RCCHAT_STRICT");
+        let flat_pages = flag("RCCHAT_FLAT_PAGES");
+        let pkg_base = pkg_base(profile_dir.as_deref(), flat_pages);
+        let icons = var("RCCHAT_ICONS")
+            .map(|v| parse_icons(&v, crate_dir, strict))
+            .unwrap_or_default();
+
+        let capture = flag("RCCHAT_CAPTURE");
+        let capture_tool = var("RCCHAT_CAPTURE_TOOL").map(|tool| crate_dir.join(tool));
+        if capture_tool.is_some() && !capture {
+            crate::warn("RCCHAT_CAPTURE_TOOL is ignored unless RCCHAT_CAPTURE is set");
+        }
+
+        let markup = if flag("RCCHAT_XHTML") {
+            Markup::Xhtml
+        } else {
+            Markup::Html
+        };
+
+        Config {
+            examples_src: var("RCCHAT_EXAMPLES_SRC").map_or_else(
+                || crate_dir.parent().unwrap().to_path_buf(),
+                |src| crate_dir.join(src),
+            ),
+            extra_roots,
+            markup,
+            script_mode: var("RCCHAT_SCRIPT_MODE").map_or(ScriptMode::Module, |v| {
+                ScriptMode::from_name(&v).unwrap_or_else(|| {
+                    panic!(
+                        "RCCHAT_SCRIPT_MODE must be `module` or `classic`, found `{}`",
+                        v
+                    )
+                })
+            }),
+            csp: csp(&pkg_base),
+            cache_control: cache_control(),
+            embed_source: flag("RCCHAT_EMBED_SOURCE"),
+            nav_bar: flag("RCCHAT_NAV_BAR"),
+            before_init: file("RCCHAT_BEFORE_INIT", crate_dir),
+            after_init: file("RCCHAT_AFTER_INIT", crate_dir),
+            sort: var("RCCHAT_SORT").map_or(SortMode::Alphabetical, |v| {
+                SortMode::from_name(&v).unwrap_or_else(|| {
+                    panic!("RCCHAT_SORT must be `name`, `mtime` or `id`, found `{}`", v)
+                })
+            }),
+            banner: file("RCCHAT_BANNER", crate_dir)
+                .filter(|_| env::var("PROFILE").as_deref() == Ok("release")),
+            only_regen: var("RCCHAT_ONLY_REGEN"),
+            landing: var("RCCHAT_LANDING_EXAMPLE"),
+            charset: charset(),
+            lang,
+            locales,
+            gzip: flag("RCCHAT_GZIP"),
+            hook: var("RCCHAT_HOOK").map(|hook| crate_dir.join(hook)),
+            server: var("RCCHAT_SERVER_CONFIG").map(|v| {
+                Server::from_name(&v).unwrap_or_else(|| {
+                    panic!(
+                        "RCCHAT_SERVER_CONFIG must be `caddy` or `nginx`, found `{}`",
+                        v
+                    )
+                })
+            }),
+            base_url,
+            json_ld: flag("RCCHAT_JSON_LD"),
+            qr_codes,
+            canonical,
+            keep: keep_list(crate_dir),
+            pkg_base,
+            jobs: jobs(),
+            csv: flag("RCCHAT_CSV"),
+            test_spec: flag("RCCHAT_TEST_SPEC"),
+            gallery_feed: flag("RCCHAT_GALLERY_FEED"),
+            checksums: flag("RCCHAT_CHECKSUMS"),
+            site_zip: flag("RCCHAT_SITE_ZIP"),
+            site_zip_pkg: flag("RCCHAT_SITE_ZIP_PKG"),
+            dependencies: flag("RCCHAT_DEPENDENCIES"),
+            badge: flag("RCCHAT_BADGE"),
+            vendored: flag("RCCHAT_VENDORED"),
+            module_docs: flag("RCCHAT_MODULE_DOCS"),
+            doc_descriptions: flag("RCCHAT_DOC_DESCRIPTIONS"),
+            flat_modules: flag("RCCHAT_FLAT_MODULES"),
+            wasm_only: flag("RCCHAT_WASM_ONLY"),
+            module_visibility: module_visibility(),
+            indent: indent(),
+            gallery: flag("RCCHAT_GALLERY"),
+            thumbnails: flag("RCCHAT_THUMBNAILS"),
+            changelog: flag("RCCHAT_CHANGELOG"),
+            service_worker: flag("RCCHAT_SERVICE_WORKER"),
+            assets_dir: var("RCCHAT_ASSETS_DIR").map(|dir| crate_dir.join(dir)),
+            strict,
+            absolute_urls,
+            theme_color: theme_color(),
+            build_info: flag("RCCHAT_BUILD_INFO").then(|| BuildInfo::detect(crate_dir)),
+            separator: separator(),
+            build_report: flag("RCCHAT_BUILD_REPORT"),
+            manifest: flag("RCCHAT_MANIFEST"),
+            icons,
+            owner: owner(),
+            lazy_init: flag("RCCHAT_LAZY_INIT"),
+            robots: robots(crate_dir),
+            ghpages: flag("RCCHAT_GHPAGES"),
+            feature_gate: flag("RCCHAT_FEATURE_GATE"),
+            run_command: run_command(),
+            profile_dir,
+            flat_pages,
+            index_authors: flag("RCCHAT_INDEX_AUTHORS"),
+            category_sections: category_sections(),
+            capture,
+            size_budget: size_budget("RCCHAT_SIZE_BUDGET"),
+            example_size_budget: size_budget("RCCHAT_EXAMPLE_SIZE_BUDGET"),
+            preload: flag("RCCHAT_PRELOAD"),
+            example_json: flag("RCCHAT_EXAMPLE_JSON"),
+            check_links: flag("RCCHAT_CHECK_LINKS"),
+            capture_tool: capture_tool.filter(|_| capture),
+            no_wasm_message: var("RCCHAT_NO_WASM_MESSAGE").unwrap_or_else(|| {
+                "This example requires WebAssembly, which is not available in this browser."
+                    .to_string()
+            }),
+            index_page_size: var("RCCHAT_INDEX_PAGE_SIZE").map(|v| match v.parse() {
+                Ok(size) if size > 0 => size,
+                _ => panic!(
+                    "RCCHAT_INDEX_PAGE_SIZE must be a positive number, found `{}`",
+                    v
+                ),
+            }),
+            max_page_name: max_page_name(),
+            link_timeout: link_timeout(),
+            skip_link: flag("RCCHAT_SKIP_LINK"),
+        }
+    }
+
+    /// The local directory of the bundle imported by the pages in `site_dir`, or `None` if it is
+    /// hosted elsewhere.
+    pub fn local_pkg_dir(&self, site_dir: &Path) -> Option<PathBuf> {
+        let pkg = &self.pkg_base;
+        if pkg.contains("://") || pkg.starts_with('/') {
+            return None;
+        }
+        // Resolved lexically, as `html` doesn't exist before the first build.
+        let mut dir = self.pages_dir(site_dir);
+        for segment in pkg.split('/') {
+            match segment {
+                ".." => {
+                    dir.pop();
+                }
+                "" | "." => (),
+                segment => dir.push(segment),
+            }
+        }
+        Some(dir)
+    }
+
+    /// The directory the pages are generated in: `crate_dir`, or the directory of the profile
+    /// inside it.
+    pub fn site_dir(&self, crate_dir: &Path) -> PathBuf {
+        match &self.profile_dir {
+            Some(profile) => crate_dir.join(profile),
+            None => crate_dir.to_path_buf(),
+        }
+    }
+
+    /// The directory of the example pages in `site_dir`.
+    pub fn pages_dir(&self, site_dir: &Path) -> PathBuf {
+        if self.flat_pages {
+            site_dir.to_path_buf()
+        } else {
+            site_dir.join("html")
+        }
+    }
+
+    /// The url of the page `page` of an example, relative to the directory of the index.
+    pub fn page_url(&self, page: &str) -> String {
+        let dir = if self.flat_pages { "" } else { "html/" };
+        format!("{}{}.{}", dir, page, self.markup.extension())
+    }
+
+    /// The url of the directory of the index, relative to the example pages.
+    pub fn pages_root(&self) -> &'static str {
+        if self.flat_pages {
+            "./"
+        } else {
+            "../"
+        }
+    }
+}
+
+/// Read the environment variable `name`, treating an empty value as unset.
+fn var(name: &str) -> Option<String> {
+    crate::rerun_if_env_changed(name);
+    // Tests start from the defaults, whatever environment they run in.
+    if cfg!(test) {
+        return None;
+    }
+    env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+/// Read the boolean environment variable `name`, which is false if unset.
+fn flag(name: &str) -> bool {
+    match var(name).as_deref() {
+        None | Some("0") | Some("false") | Some("no") | Some("off") => false,
+        Some("1") | Some("true") | Some("yes") | Some("on") => true,
+        Some(other) => panic!("{} must be a boolean, found `{}`", name, other),
+    }
+}
+
+/// Read the file named by the environment variable `name`, relative to `crate_dir`.
+fn file(name: &str, crate_dir: &Path) -> Option<String> {
+    let path = crate_dir.join(var(name)?);
+    let contents = fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("{}: failed to read {}: {}", name, path.display(), e));
+    crate::rerun_if_changed(&path);
+    Some(contents)
+}
+
+/// The character set from `RCCHAT_CHARSET`, defaulting to UTF-8.
+fn charset() -> String {
+    let charset = var("RCCHAT_CHARSET").unwrap_or_else(|| "utf-8".to_string());
+    let valid = charset
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "-_.:".contains(c));
+    if !valid {
+        panic!("RCCHAT_CHARSET: `{}` is not a character set name", charset);
+    }
+    charset
+}
+
+/// The base url from `RCCHAT_BASE_URL`, which has to be an http or https url.
+fn base_url() -> Option<String> {
+    let url = var("RCCHAT_BASE_URL")?;
+    let host = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .map_or("", |rest| rest.split('/').next().unwrap());
+    let valid = !host.is_empty()
+        // The url ends up in attributes and JavaScript string literals.
+        && !url.contains(|c: char| "\\'\"<>?#".contains(c) || c.is_whitespace() || c.is_control());
+    if !valid {
+        panic!("RCCHAT_BASE_URL: `{}` is not an http or https url", url);
+    }
+    Some(url.trim_end_matches('/').to_string())
+}
+
+/// The separator from `RCCHAT_NAME_SEPARATOR`, defaulting to `_`. It is part of identifiers and
+/// urls, so only ASCII letters, digits and `_` are allowed.
+fn separator() -> String {
+    let separator = var("RCCHAT_NAME_SEPARATOR").unwrap_or_else(|| "_".to_string());
+    if !separator
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        panic!(
+            "RCCHAT_NAME_SEPARATOR: `{}` can't be part of an identifier, only use ASCII letters, digits and `_`",
+            separator
+        );
+    }
+    separator
+}
+
+/// The longest page name from `RCCHAT_MAX_PAGE_NAME`, 100 by default. It has to leave room for
+/// the suffix of shortened names.
+fn max_page_name() -> usize {
+    match var("RCCHAT_MAX_PAGE_NAME") {
+        None => 100,
+        Some(max) => match max.parse() {
+            Ok(max) if max >= MIN_PAGE_NAME => max,
+            _ => panic!(
+                "RCCHAT_MAX_PAGE_NAME must be a number of at least {}, found `{}`",
+                MIN_PAGE_NAME, max
+            ),
+        },
+    }
+}
+
+/// The timeout of `mklink` from `RCCHAT_LINK_TIMEOUT`, in seconds, 30 by default.
+fn link_timeout() -> Duration {
+    match var("RCCHAT_LINK_TIMEOUT") {
+        None => Duration::from_secs(30),
+        Some(secs) => match secs.parse() {
+            Ok(secs) if secs > 0 => Duration::from_secs(secs),
+            _ => panic!(
+                "RCCHAT_LINK_TIMEOUT must be a positive number of seconds, found `{}`",
+                secs
+            ),
+        },
+    }
+}
+
+/// Whether the category sections of the index start expanded, from `RCCHAT_INDEX_CATEGORIES`
+/// being `expanded` or `collapsed`, or `None` without sections.
+fn category_sections() -> Option<bool> {
+    match var("RCCHAT_INDEX_CATEGORIES").as_deref() {
+        None => None,
+        Some("expanded") => Some(true),
+        Some("collapsed") => Some(false),
+        Some(other) => panic!(
+            "RCCHAT_INDEX_CATEGORIES must be `expanded` or `collapsed`, found `{}`",
+            other
+        ),
+    }
+}
+
+/// The indentation from `RCCHAT_INDENT`, a number of spaces or `tab`, four spaces by default.
+fn indent() -> String {
+    match var("RCCHAT_INDENT").as_deref() {
+        None => "    ".to_string(),
+        Some("tab") => "\t".to_string(),
+        Some(spaces) => match spaces.parse() {
+            Ok(count @ 1..=16) => " ".repeat(count),
+            _ => panic!(
+                "RCCHAT_INDENT must be a number of spaces from 1 to 16 or `tab`, found `{}`",
+                spaces
+            ),
+        },
+    }
+}
+
+/// The visibility from `RCCHAT_MODULE_VISIBILITY`: `pub`, the default, `pub(crate)` or `none`.
+fn module_visibility() -> &'static str {
+    match var("RCCHAT_MODULE_VISIBILITY").as_deref() {
+        None | Some("pub") => "pub ",
+        Some("pub(crate)") => "pub(crate) ",
+        Some("none") => "",
+        Some(other) => panic!(
+            "RCCHAT_MODULE_VISIBILITY must be `pub`, `pub(crate)` or `none`, found `{}`",
+            other
+        ),
+    }
+}
+
+/// The size budget from the environment variable `name`.
+fn size_budget(name: &str) -> Option<u64> {
+    let size = var(name)?;
+    Some(crate::budget::parse_size(&size).unwrap_or_else(|e| panic!("{}: {}", name, e)))
+}
+
+/// The owner from `RCCHAT_OWNER`, given as `uid:gid`. Ownership is only changed on unix.
+fn owner() -> Option<(u32, u32)> {
+    let value = var("RCCHAT_OWNER")?;
+    let owner = value
+        .split_once(':')
+        .and_then(|(uid, gid)| Some((uid.trim().parse().ok()?, gid.trim().parse().ok()?)))
+        .unwrap_or_else(|| {
+            panic!(
+                "RCCHAT_OWNER must be a user and group id as `uid:gid`, found `{}`",
+                value
+            )
+        });
+    if cfg!(unix) {
+        Some(owner)
+    } else {
+        crate::warn("RCCHAT_OWNER is only supported on unix, it is ignored");
+        None
+    }
+}
+
+/// The contents of `robots.txt` for the policy in `RCCHAT_ROBOTS`: `allow` or `disallow` all
+/// crawlers, or the path of a custom file relative to `crate_dir`.
+fn robots(crate_dir: &Path) -> Option<String> {
+    let robots = match var("RCCHAT_ROBOTS")?.as_str() {
+        "allow" => "User-agent: *\nDisallow:\n".to_string(),
+        "disallow" => "User-agent: *\nDisallow: /\n".to_string(),
+        _ => file("RCCHAT_ROBOTS", crate_dir).unwrap(),
+    };
+    Some(robots)
+}
+
+/// The command template from `RCCHAT_RUN_COMMAND`, defaulting to `cargo run --example {name}`, if
+/// `RCCHAT_RUN_INSTRUCTIONS` is set.
+fn run_command() -> Option<String> {
+    if !flag("RCCHAT_RUN_INSTRUCTIONS") {
+        return None;
+    }
+    let command =
+        var("RCCHAT_RUN_COMMAND").unwrap_or_else(|| "cargo run --example {name}".to_string());
+    if !command.contains("{name}") {
+        crate::warn(&format!(
+            "RCCHAT_RUN_COMMAND: `{}` doesn't contain `{{name}}`, so it is the same for all examples",
+            command
+        ));
+    }
+    Some(command)
+}
+
+/// The theme color from `RCCHAT_THEME_COLOR`, ignored with a warning if it is not a color.
+fn theme_color() -> Option<String> {
+    let color = var("RCCHAT_THEME_COLOR")?;
+    if !crate::meta::is_color(color.trim()) {
+        crate::warn(&format!(
+            "RCCHAT_THEME_COLOR: `{}` is not a color, it is ignored",
+            color
+        ));
+        return None;
+    }
+    Some(color.trim().to_string())
+}
+
+/// The number of jobs from `RCCHAT_JOBS`, defaulting to the available parallelism. Zero and
+/// invalid values fall back to rendering sequentially.
+fn jobs() -> usize {
+    match var("RCCHAT_JOBS") {
+        None => thread::available_parallelism().map_or(1, |n| n.get()),
+        Some(jobs) => match jobs.parse::<usize>() {
+            Ok(jobs) => jobs.max(1),
+            Err(_) => {
+                crate::warn(&format!(
+                    "RCCHAT_JOBS must be a number, found `{}`; rendering sequentially",
+                    jobs
+                ));
+                1
+            }
+        },
+    }
+}
+
+/// The base url of the wasm bundle from `RCCHAT_PKG_BASE`, defaulting to the relative `../pkg`.
+/// The bundle of a `profile` is in a directory named after it, and the default is relative to the
+/// pages in the directory of the profile. `flat` pages are one directory up.
+fn pkg_base(profile: Option<&str>, flat: bool) -> String {
+    let base = match var("RCCHAT_PKG_BASE") {
+        Some(base) => base,
+        None => {
+            // Module imports need a leading `./` to not be taken as a package name.
+            let up = if flat { "./" } else { "../" };
+            return match profile {
+                Some(profile) => format!("{}../pkg/{}", up, profile),
+                None => format!("{}pkg", up),
+            };
+        }
+    };
+    // The url ends up in a JavaScript string literal.
+    if base.contains(|c: char| c == '\'' || c == '\\' || c.is_control()) {
+        panic!("RCCHAT_PKG_BASE: `{}` is not a valid url", base);
+    }
+    let scheme = base.find("://").map_or("", |i| &base[..i]);
+    let absolute = !scheme.is_empty()
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+    if !absolute {
+        crate::warn(&format!(
+            "RCCHAT_PKG_BASE: `{}` is not an absolute url, it is resolved against the url of each page",
+            base
+        ));
+    }
+    let base = base.trim_end_matches('/');
+    match profile {
+        Some(profile) => format!("{}/{}", base, profile),
+        None => base.to_string(),
+    }
+}
+
+/// The caching policy from `RCCHAT_CACHE_POLICY`, if `RCCHAT_CACHE_CONTROL` is set. The default
+/// has browsers revalidate the pages, which aren't fingerprinted.
+fn cache_control() -> Option<String> {
+    if !flag("RCCHAT_CACHE_CONTROL") {
+        return None;
+    }
+    let policy = var("RCCHAT_CACHE_POLICY").unwrap_or_else(|| "no-cache".to_string());
+    // The policy ends up in an attribute.
+    if policy.contains(|c: char| "\"<>&".contains(c) || c.is_control()) {
+        panic!(
+            "RCCHAT_CACHE_POLICY: `{}` is not a Cache-Control value",
+            policy
+        );
+    }
+    Some(policy.trim().to_string())
+}
+
+/// The content security policy from `RCCHAT_CSP_POLICY`, if `RCCHAT_CSP` is set. The default
+/// allows the scripts of the origin of the pages and of the bundle at `pkg_base`, the inline
+/// scripts of the pages, and compiling WebAssembly.
+fn csp(pkg_base: &str) -> Option<String> {
+    if !flag("RCCHAT_CSP") {
+        return None;
+    }
+    if let Some(policy) = var("RCCHAT_CSP_POLICY") {
+        return Some(policy);
+    }
+    let mut policy = "script-src 'self'".to_string();
+    // A bundle hosted elsewhere is allowed by its scheme and host, e.g. `https://cdn.example.org`.
+    if let Some(i) = pkg_base.find("//") {
+        let end = pkg_base[i + 2..]
+            .find('/')
+            .map_or(pkg_base.len(), |end| i + 2 + end);
+        policy.push(' ');
+        policy.push_str(pkg_base[..end].trim_start_matches("//"));
+    }
+    policy.push_str(" 'wasm-unsafe-eval' {hashes}");
+    Some(policy)
+}
+
+fn is_language_tag(tag: &str) -> bool {
+    tag.split('-').all(|part| {
+        !part.is_empty() && part.len() <= 8 && part.chars().all(|c| c.is_ascii_alphanumeric())
+    })
+}
+
+/// The language tag from `RCCHAT_LANG`, defaulting to English.
+fn lang() -> String {
+    let lang = var("RCCHAT_LANG").unwrap_or_else(|| "en".to_string());
+    if !is_language_tag(&lang) {
+        panic!("RCCHAT_LANG: `{}` is not a language tag", lang);
+    }
+    lang
+}
+
+/// The localized deployments from `RCCHAT_LOCALES`, the comma separated list of language tags
+/// `value`, with the urls of their sites. They are found next to this one, whose `base_url` has to
+/// end with its own `lang`, e.g. `https://example.com/examples/en` and
+/// `https://example.com/examples/pt-BR`. `lang` is added to the list if missing, so that the pages
+/// refer to themselves too.
+fn locales(
+    value: Option<&str>,
+    lang: &str,
+    base_url: Option<&str>,
+    profile: Option<&str>,
+) -> Vec<(String, String)> {
+    let mut tags: Vec<String> = match value {
+        Some(value) => value
+            .split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(|tag| {
+                if !is_language_tag(tag) {
+                    panic!("RCCHAT_LOCALES: `{}` is not a language tag", tag);
+                }
+                tag.to_string()
+            })
+            .collect(),
+        None => return Vec::new(),
+    };
+    if !tags.iter().any(|tag| tag.eq_ignore_ascii_case(lang)) {
+        tags.push(lang.to_string());
+    }
+    let base_url = match base_url {
+        Some(base_url) => base_url,
+        None => {
+            crate::warn("RCCHAT_LOCALES needs RCCHAT_BASE_URL to be set, skipping it");
+            return Vec::new();
+        }
+    };
+    let parent = base_url
+        .strip_suffix(lang)
+        .and_then(|parent| parent.strip_suffix('/'))
+        .filter(|parent| parent.contains("://"))
+        .unwrap_or_else(|| {
+            panic!(
+                "RCCHAT_LOCALES: RCCHAT_BASE_URL `{}` has to end with `/{}`, the language of this \
+                 site, for the sites of the other languages to be found next to it",
+                base_url, lang
+            )
+        });
+    tags.into_iter()
+        .map(|tag| {
+            let site = match profile {
+                Some(profile) => format!("{}/{}/{}", parent, tag, profile),
+                None => format!("{}/{}", parent, tag),
+            };
+            (tag, site)
+        })
+        .collect()
+}
+
+/// Read the paths listed in the `.keep` file of `crate_dir`, one per line and relative to it. Blank
+/// lines and lines starting with `#` are ignored.
+fn keep_list(crate_dir: &Path) -> Vec<PathBuf> {
+    let path = crate_dir.join(".keep");
+    crate::rerun_if_changed(&path);
+    let list = match fs::read_to_string(&path) {
+        Ok(list) => list,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Vec::new(),
+        Err(e) => panic!("Failed to read {}: {}", path.display(), e),
+    };
+    list.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| crate_dir.join(line))
+        .collect()
+}
+
+/// Parse the comma separated list of icons in `RCCHAT_ICONS`, relative to `crate_dir`. Icons
+/// which don't exist or aren't png or svg images are warned about and left out, or panic when
+/// `strict`.
+fn parse_icons(value: &str, crate_dir: &Path, strict: bool) -> Vec<Icon> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .filter_map(|path| match Icon::load(&crate_dir.join(path)) {
+            Ok(icon) => Some(icon),
+            Err(e) if strict => panic!("RCCHAT_ICONS: {}", e),
+            Err(e) => {
+                crate::warn(&format!("RCCHAT_ICONS: {}, it is left out", e));
+                None
+            }
+        })
+        .collect()
+}
+
+/// Parse a comma separated list of `[name=]path` entries.
+fn parse_extra_roots(value: &str, crate_dir: &Path) -> Vec<ExtraRoot> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (name, path) = match entry.find('=') {
+                Some(i) => (Some(entry[..i].trim()), entry[i + 1..].trim()),
+                None => (None, entry),
+            };
+            let path = crate_dir.join(path);
+            if !path.is_dir() {
+                panic!(
+                    "RCCHAT_EXTRA_EXAMPLES: {} is not a directory",
+                    path.display()
+                );
+            }
+            let name = match name {
+                Some(name) => name.to_string(),
+                None => root_name(&path),
+            };
+            ExtraRoot { name, path }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_sites_of_other_languages() {
+        let locales = |value, lang, base_url, profile| -> Vec<(String, String)> {
+            locales(Some(value), lang, base_url, profile)
+        };
+        let pair = |tag: &str, site: &str| (tag.to_string(), site.to_string());
+        assert_eq!(
+            locales("de, pt-BR", "en", Some("https://example.com/ex/en"), None),
+            [
+                pair("de", "https://example.com/ex/de"),
+                pair("pt-BR", "https://example.com/ex/pt-BR"),
+                pair("en", "https://example.com/ex/en"),
+            ]
+        );
+        // The site refers to itself where it's listed, with its profile.
+        assert_eq!(
+            locales(
+                "fr,de",
+                "de",
+                Some("https://example.com/de"),
+                Some("release")
+            ),
+            [
+                pair("fr", "https://example.com/fr/release"),
+                pair("de", "https://example.com/de/release"),
+            ]
+        );
+        // Without a base url there is nothing to link to.
+        assert_eq!(locales("fr,de", "de", None, None), []);
+        assert_eq!(
+            super::locales(None, "de", Some("https://example.com/de"), None),
+            []
+        );
+    }
+}
@@ -0,0 +1,75 @@
+// Copyright 2020 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The inventory of the examples as CSV, for reviewing them in a spreadsheet.
+
+use crate::config::Config;
+use crate::discover::Discovery;
+
+const HEADER: &[&str] = &[
+    "name",
+    "entry_fn",
+    "html_file",
+    "skipped",
+    "reason",
+    "category",
+];
+
+/// Render one row per example, followed by the skipped ones, in RFC 4180 CSV.
+pub fn examples_csv(discovery: &Discovery, config: &Config) -> String {
+    let mut csv = row(HEADER.iter().copied());
+    for example in &discovery.examples {
+        let html_file = config.page_url(&example.page);
+        csv.push_str(&row(vec![
+            example.module_path().as_str(),
+            &example.entry,
+            &html_file,
+            "false",
+            "",
+            example.meta.category.as_deref().unwrap_or_default(),
+        ]));
+    }
+    for name in &discovery.skipped {
+        let reason = discovery.skip_reason(name).unwrap_or_default();
+        csv.push_str(&row(vec![name.as_str(), "", "", "true", &reason, ""]));
+    }
+    csv
+}
+
+fn row<'a>(fields: impl IntoIterator<Item = &'a str>) -> String {
+    let fields: Vec<String> = fields.into_iter().map(field).collect();
+    format!("{}\r\n", fields.join(","))
+}
+
+/// Quote `value` if it contains a separator, a quote or a line break.
+fn field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_fields() {
+        assert_eq!(
+            row(vec!["plain", "a, b", "say \"hi\"", "two\nlines"]),
+            "plain,\"a, b\",\"say \"\"hi\"\"\",\"two\nlines\"\r\n"
+        );
+    }
+}
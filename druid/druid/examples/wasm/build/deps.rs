@@ -0,0 +1,192 @@
+// Copyright 2020 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The APIs of druid each example exercises, scanned from its `use` statements.
+//!
+//! This is a heuristic: only `use` items starting with `druid::` are considered, so paths written
+//! out in the code or glob imports of preludes don't resolve to the items actually used.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::discover::Example;
+use crate::json::Json;
+
+/// The version of the format of `dependencies.json`, incremented on incompatible changes.
+const FORMAT_VERSION: i64 = 1;
+
+/// The crate whose APIs are looked for.
+const CRATE: &str = "druid";
+
+/// The map of each of `examples` to the APIs it imports, and of each API to the examples importing
+/// it, both sorted.
+pub fn dependencies_json(examples: &[Example]) -> Json {
+    let mut uses: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    let mut users: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for example in examples {
+        let apis = imports(&example.source);
+        for api in &apis {
+            users
+                .entry(api.clone())
+                .or_default()
+                .insert(example.module_path());
+        }
+        uses.insert(example.module_path(), apis);
+    }
+
+    let list = |items: BTreeSet<String>| Json::Array(items.into_iter().map(Json::from).collect());
+    let examples = uses
+        .into_iter()
+        .map(|(module, apis)| Json::object(vec![("module", module.into()), ("uses", list(apis))]))
+        .collect();
+    let apis = users
+        .into_iter()
+        .map(|(path, examples)| {
+            Json::object(vec![("path", path.into()), ("examples", list(examples))])
+        })
+        .collect();
+    Json::object(vec![
+        ("version", Json::Number(FORMAT_VERSION)),
+        ("crate", CRATE.into()),
+        ("examples", Json::Array(examples)),
+        ("apis", Json::Array(apis)),
+    ])
+}
+
+/// The paths imported from [`CRATE`] by the `use` statements of `source`, with aliases removed.
+fn imports(source: &str) -> BTreeSet<String> {
+    let mut paths = BTreeSet::new();
+    let mut statement: Option<String> = None;
+    for line in source.lines() {
+        let line = line.split("//").next().unwrap_or_default().trim();
+        // Statements may span several lines, up to their `;`.
+        match &mut statement {
+            Some(statement) => {
+                statement.push(' ');
+                statement.push_str(line);
+            }
+            None => match line
+                .strip_prefix("pub ")
+                .unwrap_or(line)
+                .strip_prefix("use ")
+            {
+                Some(rest) => statement = Some(rest.to_string()),
+                None => continue,
+            },
+        }
+        if let Some(text) = statement.take_if(|s| s.contains(';')) {
+            let tree = text.split(';').next().unwrap_or_default();
+            let tokens = tokenize(tree);
+            let mut expanded = Vec::new();
+            expand(&tokens, &mut 0, &[], &mut expanded);
+            paths.extend(
+                expanded
+                    .into_iter()
+                    .filter(|path| path.first().map(String::as_str) == Some(CRATE))
+                    .map(|path| path.join("::")),
+            );
+        }
+    }
+    paths
+}
+
+/// Split a use tree into identifiers, `*` and the punctuation `{`, `}` and `,`. `::` separators
+/// are dropped, as are leading ones.
+fn tokenize(tree: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    for c in tree.chars() {
+        if c.is_alphanumeric() || c == '_' {
+            word.push(c);
+            continue;
+        }
+        if !word.is_empty() {
+            tokens.push(std::mem::take(&mut word));
+        }
+        if "{},*".contains(c) {
+            tokens.push(c.to_string());
+        }
+    }
+    if !word.is_empty() {
+        tokens.push(word);
+    }
+    tokens
+}
+
+/// Expand the use tree starting at `tokens[*pos]` under `prefix` into the paths it imports, up to
+/// the `,` or `}` ending it.
+fn expand(tokens: &[String], pos: &mut usize, prefix: &[String], paths: &mut Vec<Vec<String>>) {
+    let mut path = prefix.to_vec();
+    while let Some(token) = tokens.get(*pos) {
+        *pos += 1;
+        match token.as_str() {
+            "{" => {
+                while tokens.get(*pos).is_some_and(|t| t != "}") {
+                    expand(tokens, pos, &path, paths);
+                    if tokens.get(*pos).map(String::as_str) == Some(",") {
+                        *pos += 1;
+                    }
+                }
+                *pos += 1;
+                return;
+            }
+            "," | "}" => {
+                *pos -= 1;
+                break;
+            }
+            // An alias, which doesn't change what is imported.
+            "as" => {
+                *pos += 1;
+            }
+            // `self` imports the module of the enclosing braces.
+            "self" => {}
+            token => path.push(token.to_string()),
+        }
+    }
+    if !path.is_empty() {
+        paths.push(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_use_trees() {
+        let source = r#"
+            use std::sync::Arc;
+            use druid::widget::prelude::*;
+            use druid::{
+                widget::{self, Button, Flex as Column}, // A comment with druid::Foo.
+                AppLauncher, WindowDesc,
+            };
+            pub use ::druid::kurbo::Circle;
+            // use druid::Commented;
+            use druid::AppLauncher;
+        "#;
+        let imported: Vec<String> = imports(source).into_iter().collect();
+        assert_eq!(
+            imported,
+            [
+                "druid::AppLauncher",
+                "druid::WindowDesc",
+                "druid::kurbo::Circle",
+                "druid::widget",
+                "druid::widget::Button",
+                "druid::widget::Flex",
+                "druid::widget::prelude::*",
+            ]
+        );
+    }
+}
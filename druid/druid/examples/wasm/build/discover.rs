@@ -0,0 +1,933 @@
+// Copyright 2020 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Discovery of the examples to generate pages for.
+
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsStr;
+use std::fs;
+use std::io::Result;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::compress::crc32;
+use crate::meta::{image_type, Metadata};
+use crate::EXCEPTIONS;
+
+/// Words reserved in JavaScript, which can't be used as the names of entry functions.
+const JS_RESERVED: &[&str] = &[
+    "arguments",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "eval",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+/// Keywords of Rust, including the reserved ones, which modules can only be named as raw
+/// identifiers, e.g. `r#type`.
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while", "abstract", "become", "box", "do", "final", "macro",
+    "override", "priv", "try", "typeof", "unsized", "virtual", "yield",
+];
+
+/// Keywords which can't be raw identifiers either.
+const NOT_RAW: &[&str] = &["crate", "self", "Self", "super"];
+
+/// The identifier declaring the module `name`, raw if `name` is a Rust keyword.
+pub fn module_ident(name: &str) -> String {
+    if RUST_KEYWORDS.contains(&name) {
+        format!("r#{}", name)
+    } else {
+        name.to_string()
+    }
+}
+
+/// A directory containing example source files.
+pub struct Root {
+    /// The name of the module the examples of this root are declared in, or `None` for the
+    /// primary examples directory, whose examples are declared directly in `examples`.
+    pub name: Option<String>,
+    /// The directory the examples are linked to inside this crate.
+    pub dir: PathBuf,
+}
+
+/// A single example found in one of the roots.
+pub struct Example {
+    /// The name of the enclosing root module, if the example is not in the primary root.
+    pub root: Option<String>,
+    /// The file stem of the example, which is also its module name.
+    pub stem: String,
+    /// The path of the example source file.
+    pub path: PathBuf,
+    /// The contents of the example source file, read once during discovery.
+    pub source: String,
+    /// The name of the html page (without extension) generated for the example.
+    pub page: String,
+    /// The name of the function exported to JavaScript which runs the example.
+    pub entry: String,
+    /// When the example source was last modified.
+    pub modified: SystemTime,
+    /// The persistent id of the example with `RCCHAT_SORT=id`, see [`ids`](crate::ids).
+    pub id: u64,
+    pub meta: Metadata,
+}
+
+/// The names of the pages and JSON files written next to the index, without their extension.
+const SITE_FILES: &[&str] = &[
+    "index",
+    "gallery_static",
+    "badge",
+    "build_report",
+    "captures",
+    "dependencies",
+    "gallery",
+    "test_spec",
+];
+
+/// The order in which examples are listed on the index.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortMode {
+    /// By module path.
+    Alphabetical,
+    /// Most recently modified first.
+    Modified,
+    /// By persistent id, the order in which the examples were added.
+    Id,
+}
+
+impl SortMode {
+    /// Parse the name of a sort mode, as given in `RCCHAT_SORT`.
+    pub fn from_name(name: &str) -> Option<SortMode> {
+        match name {
+            "name" => Some(SortMode::Alphabetical),
+            "mtime" => Some(SortMode::Modified),
+            "id" => Some(SortMode::Id),
+            _ => None,
+        }
+    }
+
+    /// Sort `examples` in this order. Ties are broken alphabetically, to keep the output
+    /// deterministic.
+    pub fn sort(self, examples: &mut [&Example]) {
+        examples.sort_by(|a, b| {
+            let by_name = a.module_path().cmp(&b.module_path());
+            match self {
+                SortMode::Alphabetical => by_name,
+                SortMode::Modified => b.modified.cmp(&a.modified).then(by_name),
+                SortMode::Id => a.id.cmp(&b.id).then(by_name),
+            }
+        })
+    }
+}
+
+/// The result of discovering the examples of all roots.
+pub struct Discovery {
+    /// The examples to generate pages for.
+    pub examples: Vec<Example>,
+    /// The stems of the examples found in the `EXCEPTIONS` list, and the module paths of the
+    /// examples skipped for missing features, sorted by name.
+    pub skipped: Vec<String>,
+    /// The features missing for each example skipped for them, by module path.
+    pub missing_features: BTreeMap<String, Vec<String>>,
+}
+
+impl Discovery {
+    /// Skip the examples requiring a feature for which `enabled` is false.
+    pub fn skip_missing_features(&mut self, enabled: impl Fn(&str) -> bool) {
+        let (examples, gated): (Vec<Example>, Vec<Example>) = self
+            .examples
+            .drain(..)
+            .partition(|example| example.meta.features.iter().all(|feature| enabled(feature)));
+        self.examples = examples;
+        for example in gated {
+            let missing = example
+                .meta
+                .features
+                .iter()
+                .filter(|feature| !enabled(feature))
+                .cloned()
+                .collect();
+            self.skipped.push(example.module_path());
+            self.missing_features.insert(example.module_path(), missing);
+        }
+        self.skipped.sort();
+    }
+
+    /// Shorten the page names longer than `max` characters, returning the warning about each
+    /// shortened example, or failing on the first one if `strict`.
+    ///
+    /// Names are cut and given a suffix hashing the whole name, so the same example always gets
+    /// the same page. Only the page and the files named after it change, not the module or the
+    /// entry function.
+    pub fn shorten_pages(
+        &mut self,
+        max: usize,
+        strict: bool,
+    ) -> std::result::Result<Vec<String>, String> {
+        let mut warnings = Vec::new();
+        for example in &mut self.examples {
+            if let Some(page) = shorten(&example.page, max) {
+                let message = format!(
+                    "the page name of `{}` is longer than {} characters, RCCHAT_MAX_PAGE_NAME; \
+                     it is shortened to {}",
+                    example.module_path(),
+                    max,
+                    page
+                );
+                if strict {
+                    return Err(message);
+                }
+                example.page = page;
+                warnings.push(message);
+            }
+        }
+        // A cut name could still meet the name of another example.
+        let mut pages: HashMap<String, &Example> = HashMap::new();
+        for example in &self.examples {
+            if let Some(other) = pages.insert(example.page.to_lowercase(), example) {
+                return Err(format!(
+                    "Examples `{}` and `{}` would both generate {}.html once shortened",
+                    other.module_path(),
+                    example.module_path(),
+                    example.page
+                ));
+            }
+        }
+        Ok(warnings)
+    }
+
+    /// With flat pages, check that no example page takes the name of a file written next to the
+    /// index.
+    pub fn check_flat_pages(&self) -> std::result::Result<(), String> {
+        match self
+            .examples
+            .iter()
+            .find(|e| SITE_FILES.contains(&e.page.to_lowercase().as_str()))
+        {
+            Some(example) => Err(format!(
+                "The page {} of `{}` would take the place of a file written next to the index \
+                 with RCCHAT_FLAT_PAGES; rename the example, or unset RCCHAT_FLAT_PAGES",
+                example.page,
+                example.module_path()
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Why the skipped example `name` is skipped.
+    pub fn skip_reason(&self, name: &str) -> Option<String> {
+        match self.missing_features.get(name) {
+            Some(missing) if missing.len() == 1 => {
+                Some(format!("requires the feature `{}`", missing[0]))
+            }
+            Some(missing) => Some(format!("requires the features `{}`", missing.join("`, `"))),
+            None => exception_reason(name).map(str::to_string),
+        }
+    }
+}
+
+/// The reason why the example `stem` of the primary root is skipped, if it is in `EXCEPTIONS`.
+pub fn exception_reason(stem: &str) -> Option<&'static str> {
+    EXCEPTIONS
+        .iter()
+        .find(|(name, _)| *name == stem)
+        .map(|(_, reason)| *reason)
+}
+
+/// Check that `dir`, the source directory of examples, exists before it gets linked.
+///
+/// Linking a missing directory would create a dangling link, and only fail later when discovering
+/// the examples through it.
+pub fn check_examples_dir(dir: &Path) -> std::result::Result<(), String> {
+    if dir.is_dir() {
+        return Ok(());
+    }
+    let problem = if dir.exists() {
+        "is not a directory"
+    } else {
+        "does not exist"
+    };
+    Err(format!(
+        "The examples directory {} {}. This crate expects to live inside the druid examples \
+         directory (druid/examples/wasm), next to the example sources it builds for the web, \
+         unless RCCHAT_EXAMPLES_SRC points at them.",
+        dir.display(),
+        problem
+    ))
+}
+
+/// Collect the examples of all `roots`, in the order of the roots and sorted by name within each.
+/// The pages of examples of additional roots are named `<root><separator><stem>`.
+///
+/// Examples which can't be read are skipped with a warning, or panic if `strict`. Panics if two
+/// examples would end up generating the same page or entry function.
+pub fn discover(roots: &[Root], separator: &str, strict: bool) -> Result<Discovery> {
+    let mut examples = Vec::new();
+    let mut skipped = Vec::new();
+    for root in roots {
+        let mut found = Vec::new();
+        for entry in root.dir.read_dir()? {
+            let path = entry?.path();
+            if path.extension().and_then(OsStr::to_str) != Some("rs") {
+                continue;
+            }
+            let stem = match example_stem(&path) {
+                Ok(stem) => stem.to_string(),
+                Err(e) => {
+                    crate::warn(&format!("Skipping {}: {}", path.display(), e));
+                    continue;
+                }
+            };
+
+            // Skip examples that are known to not work with wasm.
+            if root.name.is_none() && exception_reason(&stem).is_some() {
+                skipped.push(stem);
+                continue;
+            }
+            found.push((stem, path));
+        }
+        found.sort();
+
+        for (stem, path) in found {
+            // A single unreadable example, e.g. for lack of permissions, doesn't spoil the others.
+            let source = match fs::read_to_string(&path) {
+                Ok(source) => source,
+                Err(e) if strict => panic!("Failed to read {}: {}", path.display(), e),
+                Err(e) => {
+                    crate::warn(&format!(
+                        "Skipping {}: failed to read it: {}",
+                        path.display(),
+                        e
+                    ));
+                    skipped.push(match &root.name {
+                        Some(root) => format!("{}::{}", root, stem),
+                        None => stem,
+                    });
+                    continue;
+                }
+            };
+            examples.push(Example::new(
+                root.name.clone(),
+                stem,
+                path,
+                source,
+                separator,
+            ));
+        }
+    }
+
+    check_collisions(roots, &examples).unwrap_or_else(|e| panic!("{}", e));
+    for warning in duplicate_metadata(&examples) {
+        crate::warn(&warning);
+    }
+    skipped.sort();
+    Ok(Discovery {
+        examples,
+        skipped,
+        missing_features: BTreeMap::new(),
+    })
+}
+
+impl Example {
+    fn new(
+        root: Option<String>,
+        stem: String,
+        path: PathBuf,
+        source: String,
+        separator: &str,
+    ) -> Example {
+        let meta = Metadata::load(&path, &source);
+        let modified = path
+            .metadata()
+            .and_then(|m| m.modified())
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+
+        // Examples of additional roots are flattened into <root><separator><stem> to keep pages
+        // and entry functions of all roots in one namespace.
+        let page = match &root {
+            Some(root) => format!("{}{}{}", root, separator, stem),
+            None => stem.clone(),
+        };
+
+        // The entry can be renamed in the metadata. Names such as "switch" would conflict with
+        // JavaScript's switch statement, so we rename them here to e.g. switch_demo.
+        let mut entry = meta.entry.clone().unwrap_or_else(|| page.clone());
+        if JS_RESERVED.contains(&entry.as_str()) {
+            entry.push_str("_demo");
+        }
+
+        Example {
+            root,
+            stem,
+            path,
+            source,
+            page,
+            entry,
+            modified,
+            id: 0,
+            meta,
+        }
+    }
+
+    /// The module path of the example inside the `examples` module, e.g. `sibling::hello`.
+    pub fn module_path(&self) -> String {
+        match &self.root {
+            Some(root) => format!("{}::{}", root, self.stem),
+            None => self.stem.clone(),
+        }
+    }
+
+    /// The path of the copy of the example's screenshot, relative to the index, if it has one.
+    pub fn screenshot_file(&self) -> Option<String> {
+        let screenshot = self.meta.screenshot.as_ref()?;
+        Some(format!(
+            "screenshots/{}.{}",
+            self.page,
+            image_type(screenshot).unwrap_or_default()
+        ))
+    }
+
+    /// The slug of the example, see [`slug`].
+    pub fn slug(&self) -> String {
+        slug(&self.module_path())
+    }
+}
+
+/// The slug of the example with the module path `module_path`, used as its id on the index.
+///
+/// Module paths only contain ASCII identifiers separated by `::`, which is replaced by `--`. As
+/// identifiers never contain `-`, distinct examples always get distinct slugs, which are valid
+/// html and XML ids.
+pub fn slug(module_path: &str) -> String {
+    module_path.replace("::", "--")
+}
+
+/// The shortest page name `RCCHAT_MAX_PAGE_NAME` may allow, leaving room for the suffix of
+/// shortened names.
+pub const MIN_PAGE_NAME: usize = 16;
+
+/// `page` cut to `max` characters, ending with `_` and the CRC-32 of `page` in hex, if it is
+/// longer.
+fn shorten(page: &str, max: usize) -> Option<String> {
+    if page.len() <= max {
+        return None;
+    }
+    // Pages are ASCII identifiers, so this cuts on a character boundary.
+    Some(format!(
+        "{}_{:08x}",
+        &page[..max - 9],
+        crc32(page.as_bytes())
+    ))
+}
+
+/// The stem of the example at `path`, which has to be usable as a module name, a JavaScript
+/// function name and a file name in urls.
+fn example_stem(path: &Path) -> std::result::Result<&str, String> {
+    let stem = path
+        .file_stem()
+        .ok_or_else(|| "the file has no name".to_string())?
+        .to_str()
+        .ok_or_else(|| "the file name is not valid UTF-8".to_string())?;
+    if !is_identifier(stem) {
+        Err(format!("`{}` is not a valid identifier", stem))
+    } else if NOT_RAW.contains(&stem) {
+        Err(format!(
+            "`{}` is a Rust keyword which can't name a module, not even as `r#{}`",
+            stem, stem
+        ))
+    } else {
+        Ok(stem)
+    }
+}
+
+/// Whether `name` is an ASCII identifier valid in both Rust and JavaScript.
+pub fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => (),
+        _ => return false,
+    }
+    name != "_" && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// The warnings about examples sharing the same title or description, which is most likely
+/// copy-pasted and makes their cards on the index and their pages indistinguishable.
+fn duplicate_metadata(examples: &[Example]) -> Vec<String> {
+    // The examples by field and value ignoring case, with the value as written first.
+    let mut by_value: BTreeMap<(&str, String), (&str, Vec<&Example>)> = BTreeMap::new();
+    for example in examples {
+        let fields = [
+            ("description", &example.meta.description),
+            ("title", &example.meta.title),
+        ];
+        for (field, value) in &fields {
+            if let Some(value) = value {
+                by_value
+                    .entry((field, value.to_lowercase()))
+                    .or_insert_with(|| (value, Vec::new()))
+                    .1
+                    .push(example);
+            }
+        }
+    }
+    by_value
+        .iter()
+        .filter(|(_, (_, examples))| examples.len() > 1)
+        .map(|((field, _), (value, examples))| {
+            let names: Vec<String> = examples
+                .iter()
+                .map(|e| format!("`{}` ({})", e.module_path(), e.path.display()))
+                .collect();
+            format!(
+                "Examples {} share the {} \"{}\"",
+                names.join(", "),
+                field,
+                value
+            )
+        })
+        .collect()
+}
+
+/// Check that no two examples would collide with each other in the generated output.
+fn check_collisions(roots: &[Root], examples: &[Example]) -> std::result::Result<(), String> {
+    let mut pages: HashMap<String, &Example> = HashMap::new();
+    let mut entries: HashMap<&str, &Example> = HashMap::new();
+    for example in examples {
+        // Pages differing only in case collide on case-insensitive file systems, so they are
+        // rejected on all of them to keep the output portable.
+        if let Some(other) = pages.insert(example.page.to_lowercase(), example) {
+            if other.page == example.page {
+                return Err(format!(
+                    "Examples {} and {} would both generate {}.html",
+                    other.path.display(),
+                    example.path.display(),
+                    example.page
+                ));
+            }
+            return Err(format!(
+                "Examples {} and {} would generate {}.html and {}.html, which collide on \
+                 case-insensitive file systems",
+                other.path.display(),
+                example.path.display(),
+                other.page,
+                example.page
+            ));
+        }
+        // Entries may be renamed, and would then silently shadow each other in the JS imports.
+        if let Some(other) = entries.insert(&example.entry, example) {
+            return Err(format!(
+                "Examples `{}` ({}) and `{}` ({}) would both export the entry function `{}`",
+                other.module_path(),
+                other.path.display(),
+                example.module_path(),
+                example.path.display(),
+                example.entry
+            ));
+        }
+    }
+
+    // The module of an additional root must not clash with another root or an example of the
+    // primary root.
+    let mut names: HashMap<&str, &Path> = HashMap::new();
+    for root in roots {
+        if let Some(name) = &root.name {
+            if let Some(other) = names.insert(name, &root.dir) {
+                return Err(format!(
+                    "Example roots {} and {} would both declare the module `{}`",
+                    other.display(),
+                    root.dir.display(),
+                    name
+                ));
+            }
+            if let Some(example) = examples
+                .iter()
+                .find(|e| e.root.is_none() && &e.stem == name)
+            {
+                return Err(format!(
+                    "Example root {} and example {} would both declare the module `{}`",
+                    root.dir.display(),
+                    example.path.display(),
+                    name
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Derive a module name for an additional example root at `path`.
+///
+/// A directory called `examples` is named after its parent, so `../sibling/examples` becomes
+/// `sibling`.
+pub fn root_name(path: &Path) -> String {
+    let dir = match path.file_name() {
+        Some(name) if name == "examples" => path.parent().and_then(Path::file_name),
+        name => name,
+    };
+    let name: String = dir
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if name.is_empty()
+        || name.starts_with(|c: char| c.is_ascii_digit())
+        || NOT_RAW.contains(&name.as_str())
+    {
+        format!("_{}", name)
+    } else {
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn primary(dir: &Path) -> Vec<Root> {
+        vec![Root {
+            name: None,
+            dir: dir.to_path_buf(),
+        }]
+    }
+
+    fn stems(examples: &[Example]) -> Vec<&str> {
+        examples.iter().map(|e| e.stem.as_str()).collect()
+    }
+
+    #[test]
+    fn skips_stems_that_are_not_identifiers() {
+        let dir = crate::fixture_dir("invalid_stems");
+        for name in &["hello.rs", "hello-world.rs", "2d.rs", "_.rs", "calc.rs"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+
+        let examples = discover(&primary(&dir), "_", false).unwrap().examples;
+        assert_eq!(stems(&examples), ["calc", "hello"]);
+    }
+
+    #[test]
+    fn declares_keyword_stems_as_raw_identifiers() {
+        let dir = crate::fixture_dir("keyword_stems");
+        for name in &["type.rs", "mod.rs", "self.rs", "hello.rs"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+
+        let examples = discover(&primary(&dir), "_", false).unwrap().examples;
+        assert_eq!(stems(&examples), ["hello", "mod", "type"]);
+        let idents: Vec<String> = examples.iter().map(|e| module_ident(&e.stem)).collect();
+        assert_eq!(idents, ["hello", "r#mod", "r#type"]);
+        assert_eq!(examples[2].page, "type");
+        assert_eq!(root_name(Path::new("/druid/super")), "_super");
+    }
+
+    #[test]
+    fn skips_unreadable_examples() {
+        let dir = crate::fixture_dir("unreadable");
+        fs::write(dir.join("hello.rs"), "").unwrap();
+        fs::write(dir.join("latin1.rs"), b"//! Ol\xe1\n").unwrap();
+
+        let discovery = discover(&primary(&dir), "_", false).unwrap();
+        assert_eq!(stems(&discovery.examples), ["hello"]);
+        assert_eq!(discovery.skipped, ["latin1"]);
+    }
+
+    #[test]
+    fn warns_about_duplicate_titles_and_descriptions() {
+        let dir = crate::fixture_dir("duplicate_metadata");
+        fs::write(dir.join("calc.rs"), "//! description: A calculator\n").unwrap();
+        fs::write(dir.join("calc.toml"), "title = \"Demo\"").unwrap();
+        fs::write(
+            dir.join("hello.rs"),
+            "//! ```toml\n//! title = \"demo\"\n//! ```\n",
+        )
+        .unwrap();
+        fs::write(dir.join("list.rs"), "//! description: a Calculator\n").unwrap();
+        fs::write(dir.join("lens.rs"), "//! description: Lenses\n").unwrap();
+
+        let examples = discover(&primary(&dir), "_", false).unwrap().examples;
+        let path = |stem| dir.join(format!("{}.rs", stem)).display().to_string();
+        assert_eq!(
+            duplicate_metadata(&examples),
+            [
+                format!(
+                    "Examples `calc` ({}), `list` ({}) share the description \"A calculator\"",
+                    path("calc"),
+                    path("list")
+                ),
+                format!(
+                    "Examples `calc` ({}), `hello` ({}) share the title \"Demo\"",
+                    path("calc"),
+                    path("hello")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn shortens_long_page_names() {
+        let dir = crate::fixture_dir("long_stems");
+        let long = format!("a_{}", "very_long_name_".repeat(8));
+        for name in &[format!("{}.rs", long), "hello.rs".to_string()] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+
+        let mut discovery = discover(&primary(&dir), "_", false).unwrap();
+        let shortened = discovery.shorten_pages(32, false).unwrap();
+        let page = format!("a_very_long_name_very_l_{:08x}", crc32(long.as_bytes()));
+        let warning = format!(
+            "the page name of `{}` is longer than 32 characters, RCCHAT_MAX_PAGE_NAME; \
+             it is shortened to {}",
+            long, page
+        );
+        assert_eq!(shortened, [warning.as_str()]);
+        let example = &discovery.examples[0];
+        assert_eq!(
+            (example.page.as_str(), example.stem.as_str()),
+            (page.as_str(), long.as_str())
+        );
+        assert_eq!(example.entry, long);
+        assert_eq!(discovery.examples[1].page, "hello");
+
+        // The same name is always shortened the same way.
+        let mut again = discover(&primary(&dir), "_", false).unwrap();
+        assert_eq!(again.shorten_pages(32, false).unwrap(), shortened);
+
+        // Strict builds fail instead, and names within the limit are left alone either way.
+        let mut strict = discover(&primary(&dir), "_", false).unwrap();
+        assert_eq!(strict.shorten_pages(32, true), Err(warning));
+        let mut strict = discover(&primary(&dir), "_", false).unwrap();
+        assert_eq!(strict.shorten_pages(200, true), Ok(Vec::new()));
+        assert_eq!(strict.examples[0].page, long);
+    }
+
+    #[test]
+    fn missing_examples_dir_is_reported() {
+        let fixture = crate::fixture_dir("missing_examples_dir");
+        let dir = fixture.join("examples");
+        let err = check_examples_dir(&dir).unwrap_err();
+        assert!(err.contains(&format!("{} does not exist", dir.display())));
+
+        fs::write(&dir, "").unwrap();
+        let err = check_examples_dir(&dir).unwrap_err();
+        assert!(err.contains("is not a directory"));
+
+        fs::remove_file(&dir).unwrap();
+        fs::create_dir(&dir).unwrap();
+        assert!(check_examples_dir(&dir).is_ok());
+    }
+
+    #[test]
+    fn reports_exceptions_as_skipped() {
+        let dir = crate::fixture_dir("exceptions");
+        for name in &["svg.rs", "hello.rs"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+
+        let discovery = discover(&primary(&dir), "_", false).unwrap();
+        assert_eq!(stems(&discovery.examples), ["hello"]);
+        assert_eq!(discovery.skipped, ["svg"]);
+    }
+
+    #[test]
+    fn rejects_colliding_entries() {
+        let dir = crate::fixture_dir("entry_collisions");
+        fs::write(dir.join("hello.rs"), "").unwrap();
+        fs::write(dir.join("calc.rs"), "").unwrap();
+        fs::write(dir.join("calc.toml"), "entry = \"hello\"").unwrap();
+
+        let examples = vec![
+            Example::new(
+                None,
+                "calc".to_string(),
+                dir.join("calc.rs"),
+                String::new(),
+                "_",
+            ),
+            Example::new(
+                None,
+                "hello".to_string(),
+                dir.join("hello.rs"),
+                String::new(),
+                "_",
+            ),
+        ];
+        let err = check_collisions(&primary(&dir), &examples).unwrap_err();
+        assert_eq!(
+            err,
+            format!(
+                "Examples `calc` ({}) and `hello` ({}) would both export the entry function `hello`",
+                dir.join("calc.rs").display(),
+                dir.join("hello.rs").display()
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_stems_differing_in_case() {
+        let dir = crate::fixture_dir("case_collisions");
+        fs::write(dir.join("Foo.rs"), "").unwrap();
+        fs::write(dir.join("foo.rs"), "").unwrap();
+
+        let examples = vec![
+            Example::new(
+                None,
+                "Foo".to_string(),
+                dir.join("Foo.rs"),
+                String::new(),
+                "_",
+            ),
+            Example::new(
+                None,
+                "foo".to_string(),
+                dir.join("foo.rs"),
+                String::new(),
+                "_",
+            ),
+        ];
+        let err = check_collisions(&primary(&dir), &examples).unwrap_err();
+        assert_eq!(
+            err,
+            format!(
+                "Examples {} and {} would generate Foo.html and foo.html, which collide on \
+                 case-insensitive file systems",
+                dir.join("Foo.rs").display(),
+                dir.join("foo.rs").display()
+            )
+        );
+    }
+
+    #[test]
+    fn flattens_nested_examples_with_the_separator() {
+        let dir = crate::fixture_dir("separator");
+        fs::create_dir(dir.join("widgets")).unwrap();
+        fs::write(dir.join("hello.rs"), "").unwrap();
+        fs::write(dir.join("widgets").join("slider.rs"), "").unwrap();
+        let mut roots = primary(&dir);
+        roots.push(Root {
+            name: Some("widgets".to_string()),
+            dir: dir.join("widgets"),
+        });
+
+        let examples = discover(&roots, "__", false).unwrap().examples;
+        let pages: Vec<(&str, &str)> = examples
+            .iter()
+            .map(|e| (e.page.as_str(), e.entry.as_str()))
+            .collect();
+        assert_eq!(
+            pages,
+            [("hello", "hello"), ("widgets__slider", "widgets__slider")]
+        );
+    }
+
+    #[test]
+    fn rejects_collisions_introduced_by_flattening() {
+        let dir = crate::fixture_dir("flattening_collisions");
+        fs::create_dir(dir.join("widgets")).unwrap();
+        fs::write(dir.join("widgets_slider.rs"), "").unwrap();
+        fs::write(dir.join("widgets").join("slider.rs"), "").unwrap();
+        let mut roots = primary(&dir);
+        roots.push(Root {
+            name: Some("widgets".to_string()),
+            dir: dir.join("widgets"),
+        });
+        let flatten = |separator| {
+            vec![
+                Example::new(
+                    None,
+                    "widgets_slider".to_string(),
+                    dir.join("widgets_slider.rs"),
+                    String::new(),
+                    separator,
+                ),
+                Example::new(
+                    Some("widgets".to_string()),
+                    "slider".to_string(),
+                    dir.join("widgets").join("slider.rs"),
+                    String::new(),
+                    separator,
+                ),
+            ]
+        };
+
+        let err = check_collisions(&roots, &flatten("_")).unwrap_err();
+        assert!(
+            err.contains("would both generate widgets_slider.html"),
+            "{}",
+            err
+        );
+        assert!(check_collisions(&roots, &flatten("__")).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn skips_stems_that_are_not_utf8() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = crate::fixture_dir("non_utf8_stems");
+        fs::write(dir.join(OsStr::from_bytes(b"caf\xe9.rs")), "").unwrap();
+        fs::write(dir.join("hello.rs"), "").unwrap();
+
+        let examples = discover(&primary(&dir), "_", false).unwrap().examples;
+        assert_eq!(stems(&examples), ["hello"]);
+    }
+}
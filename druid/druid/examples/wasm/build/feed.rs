@@ -0,0 +1,68 @@
+// Copyright 2020 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A feed of the examples for galleries which render their cards and load the pages on demand,
+//! written to `gallery.json` next to the index.
+
+use crate::config::Config;
+use crate::discover::Example;
+use crate::json::Json;
+use crate::meta::Status;
+
+/// The version of the format of the feed, incremented on incompatible changes.
+const FORMAT_VERSION: i64 = 1;
+
+/// The feed describing the listed `examples`, in the order of the index.
+pub fn gallery_feed(examples: &[Example], config: &Config) -> Json {
+    let mut sorted: Vec<&Example> = examples.iter().filter(|e| !e.meta.unlisted).collect();
+    config.sort.sort(&mut sorted);
+    let size = |size: Option<u32>| size.map_or(Json::Null, |n| Json::Number(i64::from(n)));
+    let items = sorted
+        .into_iter()
+        .map(|e| {
+            Json::object(vec![
+                ("id", e.slug().into()),
+                (
+                    "title",
+                    e.meta
+                        .title
+                        .clone()
+                        .unwrap_or_else(|| e.module_path())
+                        .into(),
+                ),
+                (
+                    "description",
+                    Json::opt_string(e.meta.description.as_deref()),
+                ),
+                ("category", Json::opt_string(e.meta.category.as_deref())),
+                (
+                    "tags",
+                    Json::Array(e.meta.tags.iter().map(|t| t.as_str().into()).collect()),
+                ),
+                ("status", Json::opt_string(e.meta.status.map(Status::name))),
+                ("entry", e.entry.as_str().into()),
+                ("url", config.page_url(&e.page).into()),
+                ("thumbnail", Json::opt_string(e.screenshot_file())),
+                ("width", size(e.meta.width)),
+                ("height", size(e.meta.height)),
+            ])
+        })
+        .collect();
+
+    Json::object(vec![
+        ("version", Json::Number(FORMAT_VERSION)),
+        ("base_url", Json::opt_string(config.base_url.as_deref())),
+        ("examples", Json::Array(items)),
+    ])
+}
@@ -0,0 +1,208 @@
+// Copyright 2020 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A lightweight syntax highlighter for embedding example sources in pages.
+//!
+//! This doesn't tokenize Rust properly, it only recognizes enough to wrap comments, strings,
+//! numbers, keywords, types and macros in `<span>`s with a class, which the stylesheet colors.
+
+use crate::html::escape;
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while",
+];
+
+/// Highlight the Rust `source`, returning escaped html.
+pub fn highlight(source: &str) -> String {
+    let chars: Vec<char> = source.chars().collect();
+    let mut html = String::with_capacity(source.len() * 2);
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        let class = if c == '/' && chars.get(i + 1) == Some(&'/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            Some("cm")
+        } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+            i += 2;
+            // The `*` closing the comment can't be the one opening it, as in `/*/`.
+            while i < chars.len() && !(i >= start + 3 && chars[i - 1] == '*' && chars[i] == '/') {
+                i += 1;
+            }
+            i = (i + 1).min(chars.len());
+            Some("cm")
+        } else if let Some(len) = raw_string_len(&chars[i..]) {
+            i += len;
+            Some("st")
+        } else if c == '"' {
+            i += 1;
+            while i < chars.len() && chars[i] != '"' {
+                i += if chars[i] == '\\' { 2 } else { 1 };
+            }
+            i = (i + 1).min(chars.len());
+            Some("st")
+        } else if c == '\'' && is_char_literal(&chars[i..]) {
+            i += 1;
+            while i < chars.len() && chars[i] != '\'' {
+                i += if chars[i] == '\\' { 2 } else { 1 };
+            }
+            i = (i + 1).min(chars.len());
+            Some("st")
+        } else if c.is_ascii_digit() {
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            // Fractional part, but not a range such as `0..10`.
+            if chars.get(i) == Some(&'.')
+                && matches!(chars.get(i + 1), Some(c) if c.is_ascii_digit())
+            {
+                i += 1;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+            }
+            Some("nu")
+        } else if c.is_alphabetic() || c == '_' {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            if chars.get(i) == Some(&'!') && chars.get(i + 1) != Some(&'=') {
+                i += 1;
+                Some("ma")
+            } else if KEYWORDS.contains(&word.as_str()) {
+                Some("kw")
+            } else if c.is_uppercase() {
+                Some("ty")
+            } else {
+                None
+            }
+        } else {
+            i += 1;
+            None
+        };
+
+        i = i.min(chars.len());
+        let text: String = chars[start..i].iter().collect();
+        match class {
+            Some(class) => html.push_str(&format!(
+                "<span class=\"{}\">{}</span>",
+                class,
+                escape(&text)
+            )),
+            None => html.push_str(&escape(&text)),
+        }
+    }
+    html
+}
+
+/// The length of the raw string such as `r#"a"b"#` or `br"a"` at the start of `chars`, or `None`
+/// if it doesn't start with one. An unterminated raw string runs to the end.
+fn raw_string_len(chars: &[char]) -> Option<usize> {
+    let mut i = match chars {
+        ['r', ..] => 1,
+        ['b', 'r', ..] => 2,
+        _ => return None,
+    };
+    let hashes = chars[i..].iter().take_while(|c| **c == '#').count();
+    i += hashes;
+    if chars.get(i) != Some(&'"') {
+        return None;
+    }
+    let closing: Vec<char> = std::iter::once('"')
+        .chain(std::iter::repeat_n('#', hashes))
+        .collect();
+    i += 1;
+    while i < chars.len() {
+        if chars[i..].starts_with(&closing) {
+            return Some(i + closing.len());
+        }
+        i += 1;
+    }
+    Some(chars.len())
+}
+
+/// Whether the `'` at the start of `chars` opens a char literal rather than a lifetime.
+fn is_char_literal(chars: &[char]) -> bool {
+    match chars.get(1) {
+        Some('\\') => true,
+        Some(_) => chars.get(2) == Some(&'\''),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(class: &str, text: &str) -> String {
+        format!("<span class=\"{}\">{}</span>", class, text)
+    }
+
+    #[test]
+    fn highlights_comments_and_strings() {
+        assert_eq!(highlight("// a\nx"), format!("{}\nx", span("cm", "// a")));
+        assert_eq!(
+            highlight("/*/ x */y"),
+            format!("{}y", span("cm", "/*/ x */"))
+        );
+        assert_eq!(highlight("/**/y"), format!("{}y", span("cm", "/**/")));
+        assert_eq!(highlight("/* open"), span("cm", "/* open"));
+
+        assert_eq!(
+            highlight(r#""a\"b" x"#),
+            format!("{} x", span("st", r#"&quot;a\&quot;b&quot;"#))
+        );
+        assert_eq!(
+            highlight(r###"r#"a"b"# x"###),
+            format!("{} x", span("st", "r#&quot;a&quot;b&quot;#"))
+        );
+        assert_eq!(
+            highlight(r#"br"a\" x"#),
+            format!("{} x", span("st", r#"br&quot;a\&quot;"#))
+        );
+        assert_eq!(highlight("r#type"), format!("r#{}", span("kw", "type")));
+    }
+
+    #[test]
+    fn tells_char_literals_from_lifetimes() {
+        assert_eq!(highlight("'a'"), span("st", "&#39;a&#39;"));
+        assert_eq!(highlight(r"'\''"), span("st", r"&#39;\&#39;&#39;"));
+        assert_eq!(highlight("&'a str"), "&amp;&#39;a str");
+    }
+
+    #[test]
+    fn highlights_numbers_words_and_macros() {
+        assert_eq!(
+            highlight("0..10"),
+            format!("{}..{}", span("nu", "0"), span("nu", "10"))
+        );
+        assert_eq!(highlight("1.5e3"), span("nu", "1.5e3"));
+        assert_eq!(
+            highlight("println!(x)"),
+            format!("{}(x)", span("ma", "println!"))
+        );
+        assert_eq!(highlight("x!=y"), "x!=y");
+        assert_eq!(
+            highlight("let v: Vec"),
+            format!("{} v: {}", span("kw", "let"), span("ty", "Vec"))
+        );
+        assert_eq!(highlight("a < b && c"), "a &lt; b &amp;&amp; c");
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright 2020 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The discovery hook, a user provided program run with the discovered examples.
+//!
+//! This allows generating additional files off the example set without changing the build script.
+//! The hook is given the path of a JSON file describing the examples, see the README for its
+//! format.
+
+use std::path::Path;
+use std::process::Command;
+
+use crate::config::Config;
+use crate::discover::{Discovery, Example};
+use crate::json::Json;
+use crate::meta::Status;
+
+/// The version of the format of the discovery file, incremented on incompatible changes.
+const FORMAT_VERSION: i64 = 1;
+
+/// The discovery data passed to the hook.
+pub fn discovery_json(discovery: &Discovery, crate_dir: &Path, config: &Config) -> Json {
+    let ext = config.markup.extension();
+    let site_dir = config.site_dir(crate_dir);
+    let path = |p: &Path| Json::String(p.display().to_string());
+    let examples = discovery
+        .examples
+        .iter()
+        .map(|e| example_json(e, Some(&site_dir), config))
+        .collect();
+    let skipped = discovery
+        .skipped
+        .iter()
+        .map(|s| s.as_str().into())
+        .collect();
+
+    Json::object(vec![
+        ("version", Json::Number(FORMAT_VERSION)),
+        ("crate_dir", path(crate_dir)),
+        ("index", path(&site_dir.join(format!("index.{}", ext)))),
+        ("examples", Json::Array(examples)),
+        ("skipped", Json::Array(skipped)),
+    ])
+}
+
+/// The description of the example `e` in the discovery data, which is also written next to its
+/// page with `RCCHAT_EXAMPLE_JSON`. The absolute paths of its source and page are only given with
+/// the `site_dir` of the pages.
+pub fn example_json(e: &Example, site_dir: Option<&Path>, config: &Config) -> Json {
+    let path = |p: &Path| Json::String(p.display().to_string());
+    let mut members = vec![
+        ("module", e.module_path().into()),
+        ("root", Json::opt_string(e.root.as_deref())),
+        ("stem", e.stem.as_str().into()),
+        ("page", e.page.as_str().into()),
+        ("entry", e.entry.as_str().into()),
+    ];
+    if let Some(site_dir) = site_dir {
+        members.push(("source", path(&e.path)));
+        members.push(("html", path(&site_dir.join(config.page_url(&e.page)))));
+    }
+    members.extend(vec![
+        ("title", Json::opt_string(e.meta.title.as_deref())),
+        (
+            "description",
+            Json::opt_string(e.meta.description.as_deref()),
+        ),
+        (
+            "long_description",
+            Json::opt_string(e.meta.long_description.as_deref()),
+        ),
+        ("category", Json::opt_string(e.meta.category.as_deref())),
+        ("author", Json::opt_string(e.meta.author.as_deref())),
+        ("unlisted", Json::Bool(e.meta.unlisted)),
+        ("status", Json::opt_string(e.meta.status.map(Status::name))),
+        (
+            "tags",
+            Json::Array(e.meta.tags.iter().map(|t| t.as_str().into()).collect()),
+        ),
+        (
+            "features",
+            Json::Array(e.meta.features.iter().map(|f| f.as_str().into()).collect()),
+        ),
+    ]);
+    Json::object(members)
+}
+
+/// Write the discovery data to `out_dir` and run `hook` with its path as the only argument.
+///
+/// Panics if the hook can't be run or fails.
+pub fn run(hook: &Path, data: &Json, crate_dir: &Path, out_dir: &Path) {
+    let data_path = out_dir.join("discovery.json");
+    crate::output::replace(&data_path, data.to_string())
+        .unwrap_or_else(|e| panic!("Failed to write {}: {}", data_path.display(), e));
+
+    let status = Command::new(hook)
+        .arg(&data_path)
+        .current_dir(crate_dir)
+        .status()
+        .unwrap_or_else(|e| panic!("RCCHAT_HOOK: failed to run {}: {}", hook.display(), e));
+    if !status.success() {
+        panic!("RCCHAT_HOOK: {} failed with {}", hook.display(), status);
+    }
+}
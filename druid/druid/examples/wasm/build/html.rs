@@ -0,0 +1,2200 @@
+// Copyright 2020 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Templates of the generated index and example pages.
+
+use crate::config::Config;
+use crate::digest::{base64, sha256};
+use crate::discover::{slug, Example};
+use crate::highlight::highlight;
+use crate::info::BuildInfo;
+use crate::json::Json;
+use crate::manifest::touch_icon;
+use crate::meta::{Feature, Status};
+use crate::output::checksum;
+use crate::qr;
+
+/// The page body used by examples that don't supply their own in their metadata.
+const DEFAULT_BODY: &str = r#"<canvas id="canvas"></canvas>"#;
+
+/// The page body of non-graphical examples, showing what they log.
+const OUTPUT_BODY: &str = r#"<pre id="output" class="output"></pre>"#;
+
+/// Copies what the example logs to the console into the output area of a non-graphical page, as
+/// wasm logging crates and panic hooks log through the console.
+const OUTPUT_SCRIPT: &str = r#"
+            {
+                const output = document.getElementById('output');
+                for (const level of ['log', 'info', 'warn', 'error']) {
+                    const original = console[level];
+                    console[level] = (...args) => {
+                        original.apply(console, args);
+                        if (output) {
+                            output.append(args.join(' ') + '\n');
+                        }
+                    };
+                }
+            }
+        "#;
+
+/// How the pages load the bundle, which has to match the wasm-pack target it was built for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScriptMode {
+    /// A module script importing the bundle, built with `--target web`.
+    Module,
+    /// A classic script calling the global `wasm_bindgen` of the bundle, built with
+    /// `--target no-modules`, for hosts whose policies disallow module scripts.
+    Classic,
+}
+
+impl ScriptMode {
+    /// Parse the name of a script mode, as given in `RCCHAT_SCRIPT_MODE`.
+    pub fn from_name(name: &str) -> Option<ScriptMode> {
+        match name {
+            "module" => Some(ScriptMode::Module),
+            "classic" => Some(ScriptMode::Classic),
+            _ => None,
+        }
+    }
+
+    /// The wasm-pack target building a bundle for this mode.
+    fn target(self) -> &'static str {
+        match self {
+            ScriptMode::Module => "web",
+            ScriptMode::Classic => "no-modules",
+        }
+    }
+}
+
+/// The flavour of markup the pages are generated in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Markup {
+    /// HTML5, served as `text/html`.
+    Html,
+    /// Well-formed XHTML, for hosts serving pages as `application/xhtml+xml`.
+    Xhtml,
+}
+
+impl Markup {
+    /// The file extension of pages in this markup.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Markup::Html => "html",
+            Markup::Xhtml => "xhtml",
+        }
+    }
+
+    /// The start of a document, up to and including the opening `<html>` tag.
+    fn document_start(self, config: &Config) -> String {
+        match self {
+            Markup::Html => format!("<!DOCTYPE html>\n<html lang=\"{}\">", config.lang),
+            Markup::Xhtml => format!(
+                concat!(
+                    "<?xml version=\"1.0\" encoding=\"{charset}\"?>\n",
+                    "<!DOCTYPE html>\n",
+                    "<html xmlns=\"http://www.w3.org/1999/xhtml\" lang=\"{lang}\" xml:lang=\"{lang}\">"
+                ),
+                charset = config.charset,
+                lang = config.lang
+            ),
+        }
+    }
+
+    /// A void element such as `<meta>`, which has to be self-closed in XHTML.
+    fn void(self, tag: &str) -> String {
+        match self {
+            Markup::Html => format!("<{}>", tag),
+            Markup::Xhtml => format!("<{} />", tag),
+        }
+    }
+
+    /// Wrap the contents of an inline `<script>` or `<style>` so that they remain valid when the
+    /// page is parsed as XML. `comment` is the pair of comment delimiters of the wrapped language.
+    fn raw_text(self, text: &str, comment: (&str, &str)) -> String {
+        match self {
+            Markup::Html => text.to_string(),
+            Markup::Xhtml => format!(
+                "{open}<![CDATA[{close}{text}{open}]]>{close}",
+                open = comment.0,
+                close = comment.1,
+                text = text
+            ),
+        }
+    }
+}
+
+/// Client side filtering of the index by tags. An entry is shown if it has all selected tags, or
+/// no tags at all.
+const TAG_FILTER_SCRIPT: &str = r#"
+            const selected = new Set();
+            const chips = document.querySelectorAll('.tags button');
+            const entries = document.querySelectorAll('li[data-tags]');
+            for (const chip of chips) {
+                chip.addEventListener('click', () => {
+                    const tag = chip.dataset.tag;
+                    if (!selected.delete(tag)) {
+                        selected.add(tag);
+                    }
+                    chip.setAttribute('aria-pressed', selected.has(tag));
+                    for (const entry of entries) {
+                        const tags = entry.dataset.tags.split(' ');
+                        const visible = [...selected].every(tag => tags.includes(tag));
+                        entry.hidden = !visible;
+                    }
+                });
+            }
+        "#;
+
+/// Added to the tag filter with category sections: sections with matching entries are expanded
+/// while filtering, and sections without any are hidden.
+const SECTION_FILTER_SCRIPT: &str = r#"
+            for (const chip of chips) {
+                chip.addEventListener('click', () => {
+                    for (const section of document.querySelectorAll('details.category')) {
+                        const matches = section.querySelector('li:not([hidden])') !== null;
+                        section.hidden = !matches;
+                        if (matches && selected.size > 0) {
+                            section.open = true;
+                        }
+                    }
+                });
+            }
+        "#;
+
+/// Expands the collapsed category section holding the entry linked to, as in `index.html#hello`.
+const SECTION_TARGET_SCRIPT: &str = r#"
+            function reveal() {
+                const target = location.hash && document.getElementById(location.hash.slice(1));
+                const section = target && target.closest('details.category');
+                if (section) {
+                    section.open = true;
+                    target.scrollIntoView();
+                }
+            }
+            reveal();
+            addEventListener('hashchange', reveal);
+        "#;
+
+/// Shows the headings of the category sections next to their disclosure triangle.
+const SECTION_STYLE: &str = r#"
+            details.category summary {
+                cursor: pointer;
+            }
+            details.category summary h2 {
+                display: inline;
+            }
+        "#;
+
+const TAG_STYLE: &str = r#"
+            .tags button {
+                border: 1px solid #888;
+                border-radius: 1em;
+                background: none;
+                padding: 0.2em 0.8em;
+                margin: 0 0.2em 0.4em 0;
+                cursor: pointer;
+            }
+            .tags button[aria-pressed="true"] {
+                background: #333;
+                color: #fff;
+            }
+        "#;
+
+/// Highlights the entry linked to with e.g. `index.html#hello`, and wraps long names.
+const INDEX_STYLE: &str = r#"
+            li {
+                overflow-wrap: anywhere;
+            }
+            li:target {
+                background: #fff8c5;
+            }
+            .thumbnail {
+                display: block;
+                width: 160px;
+                height: 100px;
+                object-fit: cover;
+            }
+        "#;
+
+/// The landing demo at the top of the index.
+const LANDING_DEMO_STYLE: &str = r#"
+            figure.landing {
+                margin: 1em 0;
+            }
+            figure.landing iframe {
+                display: block;
+                width: 100%;
+                height: 60vh;
+                max-height: 80vh;
+                border: 1px solid #d0d7de;
+            }
+            figure.landing figcaption {
+                margin-top: 0.3em;
+            }
+        "#;
+
+/// The default colors of the status badges.
+const BADGE_STYLE: &str = r#"
+            .badge {
+                display: inline-block;
+                border-radius: 0.3em;
+                padding: 0 0.4em;
+                margin-left: 0.4em;
+                font: 0.75em sans-serif;
+                color: #fff;
+                vertical-align: middle;
+            }
+            .badge.stable { background: #2da44e; }
+            .badge.experimental { background: #bf8700; }
+            .badge.broken { background: #cf222e; }
+            .badge.requires { background: #57606a; }
+            .badge.feature { background: #0969da; }
+        "#;
+
+/// The badges of `example` on the index: its status, and the features it requires.
+fn badges(example: &Example) -> String {
+    let mut badges = example.meta.status.map(badge).unwrap_or_default();
+    for feature in &example.meta.requires {
+        badges.push_str(&format!(
+            " <span class=\"badge requires\">{}</span>",
+            feature.label()
+        ));
+    }
+    for feature in &example.meta.features {
+        badges.push_str(&format!(
+            " <span class=\"badge feature\">feature {}</span>",
+            feature
+        ));
+    }
+    badges
+}
+
+/// A badge showing `status`, preceded by a space.
+fn badge(status: Status) -> String {
+    format!(
+        " <span class=\"badge {name}\">{name}</span>",
+        name = status.name()
+    )
+}
+
+/// The navigation between the pages of a paginated index.
+const PAGES_STYLE: &str = r#"
+            nav.pages a, nav.pages span {
+                margin-right: 0.5em;
+            }
+        "#;
+
+/// The file name of the index page `number`, counting from 1: `index.html`, `index-2.html`, etc.
+pub fn index_file(number: usize, config: &Config) -> String {
+    match number {
+        1 => format!("index.{}", config.markup.extension()),
+        n => format!("index-{}.{}", n, config.markup.extension()),
+    }
+}
+
+/// Render the index listing the `examples` which aren't unlisted, followed by the `skipped` ones
+/// which have no page, returning the file name and contents of each of its pages.
+///
+/// The index is a single page unless `RCCHAT_INDEX_PAGE_SIZE` is set. The examples are then split
+/// in their sort order, and the skipped ones are listed on the last page.
+pub fn index_pages(
+    examples: &[Example],
+    skipped: &[String],
+    config: &Config,
+) -> Vec<(String, String)> {
+    let mut sorted: Vec<&Example> = examples.iter().filter(|e| !e.meta.unlisted).collect();
+    config.sort.sort(&mut sorted);
+    let structured = structured_data(&sorted, config);
+    let landing = landing_demo(examples, skipped, config);
+
+    let size = config.index_page_size.unwrap_or(usize::MAX);
+    let mut chunks: Vec<&[&Example]> = sorted.chunks(size).collect();
+    if chunks.is_empty() {
+        chunks.push(&[]);
+    }
+    let count = chunks.len();
+    chunks
+        .into_iter()
+        .zip(1..)
+        .map(|(chunk, number)| {
+            let skipped = if number == count { skipped } else { &[] };
+            // The structured data lists all examples, so it's only on the first page, as is the
+            // landing demo.
+            let (structured, landing) = if number == 1 {
+                (structured.as_str(), landing.as_str())
+            } else {
+                ("", "")
+            };
+            let page = index_page(chunk, skipped, structured, landing, (number, count), config);
+            (index_file(number, config), page)
+        })
+        .collect()
+}
+
+/// With `RCCHAT_LANDING_EXAMPLE`, the page of that example embedded live, with a caption linking to
+/// it. It only loads once scrolled to. An example which is skipped or doesn't exist is warned
+/// about, and the index has no landing demo.
+fn landing_demo(examples: &[Example], skipped: &[String], config: &Config) -> String {
+    let name = match &config.landing {
+        Some(name) => name,
+        None => return String::new(),
+    };
+    let example = match examples.iter().find(|e| &e.page == name) {
+        Some(example) => example,
+        None => {
+            let problem = if skipped.contains(name) {
+                "is skipped"
+            } else {
+                "doesn't exist"
+            };
+            crate::warn(&format!(
+                "RCCHAT_LANDING_EXAMPLE: the example `{}` {}, so the index has no landing demo",
+                name, problem
+            ));
+            return String::new();
+        }
+    };
+    let url = format!("{}{}", root(config, "./"), config.page_url(&example.page));
+    // The iframe keeps the shape the example is best shown at, if known.
+    let shape = match (example.meta.width, example.meta.height) {
+        (Some(width), Some(height)) => format!(
+            " style=\"aspect-ratio: {} / {}; height: auto;\"",
+            width, height
+        ),
+        _ => String::new(),
+    };
+    let description = match &example.meta.description {
+        Some(description) => format!(": {}", escape(description)),
+        None => String::new(),
+    };
+    format!(
+        r#"
+        <figure class="landing">
+            <iframe src="{url}" title="{name}" loading="lazy"{shape}></iframe>
+            <figcaption><a href="{url}">{name}</a>{description}</figcaption>
+        </figure>"#,
+        url = url,
+        name = example.module_path(),
+        shape = shape,
+        description = description,
+    )
+}
+
+/// The footer of the index, telling which build produced it.
+fn footer(info: &BuildInfo) -> String {
+    let mut parts = vec![format!("Version {}", escape(&info.version))];
+    if let Some(commit) = &info.commit {
+        parts.push(format!("commit <code>{}</code>", commit));
+        parts.push(format!("built {}", info.date));
+    }
+    format!("\n        <footer>{}</footer>", parts.join(", "))
+}
+
+/// The links to the other pages of the index, from the page `number` of `count`.
+fn page_nav(number: usize, count: usize, config: &Config) -> String {
+    let root = root(config, "./");
+    let link = |n: usize, text: &str| {
+        format!("<a href=\"{}{}\">{}</a>", root, index_file(n, config), text)
+    };
+    let mut nav = String::new();
+    if number > 1 {
+        nav.push_str(&link(number - 1, "&#8592; Previous"));
+    }
+    for n in 1..=count {
+        if n == number {
+            nav.push_str(&format!("<span aria-current=\"page\">{}</span>", n));
+        } else {
+            nav.push_str(&link(n, &n.to_string()));
+        }
+    }
+    if number < count {
+        nav.push_str(&link(number + 1, "Next &#8594;"));
+    }
+    format!(
+        "\n        <nav class=\"pages\" aria-label=\"Pages\">{}</nav>",
+        nav
+    )
+}
+
+/// With `RCCHAT_LOCALES`, links to the index page `number` in each language, and to the one in
+/// the first language listed for visitors speaking none of them.
+fn alternates(number: usize, config: &Config) -> String {
+    let file = index_file(number, config);
+    let link = |tag: &str, site: &str| {
+        format!(
+            "\n        {}",
+            config.markup.void(&format!(
+                "link rel=\"alternate\" hreflang=\"{}\" href=\"{}/{}\"",
+                tag, site, file
+            ))
+        )
+    };
+    let mut links: String = config
+        .locales
+        .iter()
+        .map(|(tag, site)| link(tag, site))
+        .collect();
+    if let Some((_, site)) = config.locales.first() {
+        links.push_str(&link("x-default", site));
+    }
+    links
+}
+
+/// Render one page of the index, the page `number` of `count`, listing `examples` in order.
+fn index_page(
+    examples: &[&Example],
+    skipped: &[String],
+    structured: &str,
+    landing: &str,
+    (number, count): (usize, usize),
+    config: &Config,
+) -> String {
+    let markup = config.markup;
+    let mut entries: Vec<(Option<&str>, String)> = Vec::new();
+    for &example in examples {
+        let tags = if example.meta.tags.is_empty() {
+            String::new()
+        } else {
+            format!(" data-tags=\"{}\"", escape(&example.meta.tags.join(" ")))
+        };
+        let thumbnail = if config.thumbnails {
+            markup.void(&format!(
+                "img class=\"thumbnail\" src=\"{}\" alt=\"\"",
+                escape(&thumbnail_url(example, config))
+            ))
+        } else {
+            String::new()
+        };
+        let author = match &example.meta.author {
+            Some(author) if config.index_authors => {
+                format!(" <small class=\"author\">by {}</small>", escape(author))
+            }
+            _ => String::new(),
+        };
+        let entry = format!(
+            "<li id=\"{id}\"{tags}><a href=\"{root}{page}\">{thumbnail}{name}</a>{author}{badge}</li>",
+            root = root(config, "./"),
+            id = example.slug(),
+            tags = tags,
+            page = config.page_url(&example.page),
+            thumbnail = thumbnail,
+            name = example.module_path(),
+            author = author,
+            badge = badges(example),
+        );
+        entries.push((example.meta.category.as_deref(), entry));
+    }
+    // The skipped examples have no metadata, they go with the uncategorized ones.
+    for stem in skipped {
+        let entry = format!(
+            "<li id=\"{}\">{}{}</li>",
+            slug(stem),
+            stem,
+            badge(Status::Broken)
+        );
+        entries.push((None, entry));
+    }
+    let list = |entries: &[&str]| format!("<ul>\n{}</ul>", entries.concat());
+    let (lists, section_head) = match config.category_sections {
+        None => {
+            let entries: Vec<&str> = entries.iter().map(|(_, entry)| entry.as_str()).collect();
+            (list(&entries), String::new())
+        }
+        Some(open) => {
+            // Categories are sorted by name, followed by the examples without one.
+            let mut categories: Vec<Option<&str>> = entries.iter().map(|(c, _)| *c).collect();
+            categories.sort_by_key(|c| (c.is_none(), c.map(str::to_lowercase), *c));
+            categories.dedup();
+            let sections: Vec<String> = categories
+                .into_iter()
+                .map(|category| {
+                    let entries: Vec<&str> = entries
+                        .iter()
+                        .filter(|(c, _)| *c == category)
+                        .map(|(_, entry)| entry.as_str())
+                        .collect();
+                    format!(
+                        "<details class=\"category\"{open}>\n            <summary><h2>{name}</h2></summary>\n            {list}\n        </details>",
+                        open = if open { " open=\"open\"" } else { "" },
+                        name = escape(category.unwrap_or("Other")),
+                        list = list(&entries),
+                    )
+                })
+                .collect();
+            let mut head = format!(
+                "\n        <style>{}</style>",
+                markup.raw_text(SECTION_STYLE, ("/*", "*/"))
+            );
+            if !open {
+                head.push_str(&format!(
+                    "\n        <script>{}</script>",
+                    markup.raw_text(SECTION_TARGET_SCRIPT, ("//", "\n"))
+                ));
+            }
+            (sections.join("\n        "), head)
+        }
+    };
+    let badges = if skipped.is_empty()
+        && examples.iter().all(|e| {
+            e.meta.status.is_none() && e.meta.requires.is_empty() && e.meta.features.is_empty()
+        }) {
+        String::new()
+    } else {
+        format!(
+            "\n        <style>{}</style>",
+            markup.raw_text(BADGE_STYLE, ("/*", "*/"))
+        )
+    };
+
+    let (nav, pages_style) = if count > 1 {
+        (
+            page_nav(number, count, config),
+            format!(
+                "\n        <style>{}</style>",
+                markup.raw_text(PAGES_STYLE, ("/*", "*/"))
+            ),
+        )
+    } else {
+        (String::new(), String::new())
+    };
+    let title = if count > 1 {
+        format!("index, page {} of {}", number, count)
+    } else {
+        "index".to_string()
+    };
+
+    // Offer a filter chip for every tag in use on this page.
+    let mut tags: Vec<&str> = examples
+        .iter()
+        .flat_map(|e| e.meta.tags.iter().map(String::as_str))
+        .collect();
+    tags.sort_unstable();
+    tags.dedup();
+    let (filter, head, script) = if tags.is_empty() {
+        (String::new(), String::new(), String::new())
+    } else {
+        let filter_script = match config.category_sections {
+            Some(_) => format!(
+                "{}{}",
+                TAG_FILTER_SCRIPT.trim_end_matches(' '),
+                SECTION_FILTER_SCRIPT.trim_start_matches('\n')
+            ),
+            None => TAG_FILTER_SCRIPT.to_string(),
+        };
+        let chips: String = tags
+            .iter()
+            .map(|tag| {
+                format!(
+                    "<button type=\"button\" data-tag=\"{tag}\" aria-pressed=\"false\">{tag}</button>",
+                    tag = escape(tag)
+                )
+            })
+            .collect();
+        (
+            format!("\n        <div class=\"tags\">{}</div>", chips),
+            format!(
+                "\n        <style>{}</style>",
+                markup.raw_text(TAG_STYLE, ("/*", "*/"))
+            ),
+            format!(
+                "\n        <script>{}</script>",
+                markup.raw_text(&filter_script, ("//", "\n"))
+            ),
+        )
+    };
+
+    let page = format!(
+        r#"{start}
+    <head>
+        {meta}
+        <title>Druid WASM examples - {title}</title>
+        <style>{index_style}</style>{landing_style}{badges}{pages_style}{head}{section_head}{structured}{alternates}
+    </head>
+    <body>
+        <h1>Druid WASM examples</h1>{landing}{filter}
+        {lists}{nav}{footer}{script}{body_end}</body></html>"#,
+        start = markup.document_start(config),
+        meta = head_meta(config, config.theme_color.as_deref(), &root(config, "./")),
+        title = title,
+        index_style = markup.raw_text(INDEX_STYLE, ("/*", "*/")),
+        badges = badges,
+        pages_style = pages_style,
+        nav = nav,
+        footer = config.build_info.as_ref().map(footer).unwrap_or_default(),
+        head = head,
+        structured = structured,
+        alternates = alternates(number, config),
+        landing_style = if landing.is_empty() {
+            String::new()
+        } else {
+            format!(
+                "\n        <style>{}</style>",
+                markup.raw_text(LANDING_DEMO_STYLE, ("/*", "*/"))
+            )
+        },
+        landing = landing,
+        filter = filter,
+        section_head = section_head,
+        lists = lists,
+        script = script,
+        body_end = body_end(config, &root(config, "./")),
+    );
+    with_csp(page, config)
+}
+
+/// A JSON-LD `ItemList` of the example pages for search engines, if enabled and the base url is
+/// known.
+fn structured_data(examples: &[&Example], config: &Config) -> String {
+    let base_url = match (&config.base_url, config.json_ld) {
+        (Some(base_url), true) => base_url,
+        (None, true) => {
+            crate::warn("RCCHAT_JSON_LD needs RCCHAT_BASE_URL to be set, skipping it");
+            return String::new();
+        }
+        (_, false) => return String::new(),
+    };
+    let items = examples
+        .iter()
+        .zip(1..)
+        .map(|(example, position)| {
+            let mut item = vec![
+                ("@type", "ListItem".into()),
+                ("position", Json::Number(position)),
+                (
+                    "url",
+                    format!("{}/{}", base_url, config.page_url(&example.page)).into(),
+                ),
+                ("name", example.module_path().into()),
+            ];
+            if let Some(description) = &example.meta.description {
+                item.push(("description", description.as_str().into()));
+            }
+            Json::object(item)
+        })
+        .collect();
+    let list = Json::object(vec![
+        ("@context", "https://schema.org".into()),
+        ("@type", "ItemList".into()),
+        ("name", "Druid WASM examples".into()),
+        ("itemListElement", Json::Array(items)),
+    ]);
+    // The JSON escapes `<`, `>` and `&`, so it can't end the script early or break XHTML.
+    format!(
+        "\n        <script type=\"application/ld+json\">{}</script>",
+        list
+    )
+}
+
+const GALLERY_STYLE: &str = r#"
+            body {
+                font-family: sans-serif;
+                max-width: 60em;
+                margin: 0 auto;
+                padding: 1em;
+            }
+            figure {
+                display: inline-block;
+                vertical-align: top;
+                width: 18em;
+                margin: 0 1em 1.5em 0;
+                break-inside: avoid;
+            }
+            figure img {
+                width: 100%;
+                border: 1px solid #ccc;
+            }
+            @media print {
+                body > p {
+                    display: none;
+                }
+                body {
+                    max-width: none;
+                    padding: 0;
+                }
+            }
+        "#;
+
+/// Render a static page showing the screenshot and description of each listed example, for
+/// browsers which can't run WASM.
+pub fn gallery_page(examples: &[Example], config: &Config) -> String {
+    let markup = config.markup;
+    let mut sorted: Vec<&Example> = examples.iter().filter(|e| !e.meta.unlisted).collect();
+    config.sort.sort(&mut sorted);
+
+    let mut figures = String::new();
+    for example in sorted {
+        let name = escape(&example.module_path());
+        let image = match example.screenshot_file() {
+            Some(file) => format!(
+                "\n            {}",
+                markup.void(&format!(
+                    "img src=\"{}{}\" alt=\"Screenshot of {}\"",
+                    root(config, "./"),
+                    file,
+                    name
+                ))
+            ),
+            None if config.thumbnails => format!(
+                "\n            {}",
+                markup.void(&format!(
+                    "img src=\"{}\" alt=\"\"",
+                    escape(&placeholder(&example.module_path()))
+                ))
+            ),
+            None => String::new(),
+        };
+        let description = match &example.meta.description {
+            Some(description) => format!("{}{}", markup.void("br"), escape(description)),
+            None => String::new(),
+        };
+        figures.push_str(&format!(
+            "\n        <figure id=\"{id}\">{image}\n            <figcaption><strong>{name}</strong>{description}</figcaption>\n        </figure>",
+            id = example.slug(),
+            image = image,
+            name = name,
+            description = description
+        ));
+    }
+
+    let page = format!(
+        r#"{start}
+    <head>
+        {meta}
+        <title>Druid WASM examples - gallery</title>
+        <style>{style}</style>
+    </head>
+    <body>
+        <h1>Druid WASM examples</h1>
+        <p>These are screenshots of the examples. Running the live demos requires a browser with
+        WebAssembly and JavaScript enabled, see the <a href="{root}index.{ext}">index</a>.</p>{figures}{body_end}
+    </body>
+</html>"#,
+        start = markup.document_start(config),
+        meta = head_meta(config, config.theme_color.as_deref(), &root(config, "./")),
+        style = markup.raw_text(GALLERY_STYLE, ("/*", "*/")),
+        root = root(config, "./"),
+        ext = markup.extension(),
+        figures = figures,
+        body_end = body_end(config, &root(config, "./")),
+    );
+    with_csp(page, config)
+}
+
+/// The profiles linked from the landing page of pages per profile.
+const PROFILES: &[&str] = &["debug", "release"];
+
+const LANDING_STYLE: &str = r#"
+            body {
+                font-family: sans-serif;
+                max-width: 60em;
+                margin: 0 auto;
+                padding: 1em;
+            }
+        "#;
+
+/// Render the landing page linking the index of each profile, with pages per profile.
+pub fn profiles_page(config: &Config) -> String {
+    let markup = config.markup;
+    let mut links = String::new();
+    for profile in PROFILES {
+        links.push_str(&format!(
+            "\n            <li><a href=\"{profile}/index.{ext}\">{profile}</a></li>",
+            profile = profile,
+            ext = markup.extension()
+        ));
+    }
+    let page = format!(
+        r#"{start}
+    <head>
+        {charset}
+        {viewport}
+        <title>Druid WASM examples</title>
+        <style>{style}</style>
+    </head>
+    <body>
+        <h1>Druid WASM examples</h1>
+        <p>The examples are built with each profile, to compare them side by side. The pages of a
+        profile are only there once it has been built.</p>
+        <ul>{links}
+        </ul>
+    </body>
+</html>"#,
+        start = markup.document_start(config),
+        charset = markup.void(&format!("meta charset=\"{}\"", config.charset)),
+        viewport =
+            markup.void("meta name=\"viewport\" content=\"width=device-width, initial-scale=1\""),
+        style = markup.raw_text(LANDING_STYLE, ("/*", "*/")),
+        links = links,
+    );
+    with_csp(page, config)
+}
+
+/// The url of the thumbnail of `example`: its screenshot, or a placeholder if it has none.
+fn thumbnail_url(example: &Example, config: &Config) -> String {
+    match example.screenshot_file() {
+        Some(file) => format!("{}{}", root(config, "./"), file),
+        None => placeholder(&example.module_path()),
+    }
+}
+
+/// A placeholder thumbnail for the example `name`, as an svg data url. The color is derived from
+/// the name, so that it stays the same across builds.
+fn placeholder(name: &str) -> String {
+    let hue = checksum(name.as_bytes()) % 360;
+    let svg = format!(
+        concat!(
+            "<svg xmlns='http://www.w3.org/2000/svg' width='160' height='100'>",
+            "<rect width='160' height='100' fill='hsl({hue},55%,55%)'/>",
+            "<text x='80' y='55' font-family='sans-serif' font-size='14' fill='#fff' ",
+            "text-anchor='middle'>{name}</text>",
+            "</svg>"
+        ),
+        hue = hue,
+        name = escape(name)
+    );
+    svg_data_url(&svg)
+}
+
+/// The data url of the `svg` image, percent-encoded as browsers require.
+fn svg_data_url(svg: &str) -> String {
+    let mut url = "data:image/svg+xml,".to_string();
+    for b in svg.bytes() {
+        if b.is_ascii_alphanumeric() || b"-_.~'=/:,()".contains(&b) {
+            url.push(b as char);
+        } else {
+            url.push_str(&format!("%{:02X}", b));
+        }
+    }
+    url
+}
+
+/// Escape `text` for use in html text and attribute values.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The shared stylesheet, written to `style.css` next to the index.
+pub const STYLESHEET: &str = r#"/* This file is automatically generated and must not be committed. */
+
+/* Syntax highlighting of embedded example sources. */
+.source {
+    margin: 0;
+    padding: 1em;
+    overflow: auto;
+    background: #f6f8fa;
+    color: #24292e;
+    font-size: 0.9em;
+}
+.source .cm { color: #6a737d; font-style: italic; }
+.source .st { color: #032f62; }
+.source .nu { color: #005cc5; }
+.source .kw { color: #d73a49; }
+.source .ty { color: #6f42c1; }
+.source .ma { color: #e36209; }
+
+/* Printed pages leave out the example and navigation, and wrap the source to the paper. */
+@media print {
+    /* Overrides the inline styles of the pages. */
+    nav, canvas, button.run, details.run-locally {
+        display: none !important;
+    }
+    .source {
+        overflow: visible;
+        white-space: pre-wrap;
+        overflow-wrap: anywhere;
+        background: none;
+        font-size: 9pt;
+    }
+}
+"#;
+
+/// The overlay of pages which only run their example once asked to.
+const RUN_STYLE: &str = r#"
+            button.run {
+                position: fixed;
+                top: 35vh;
+                left: 50%;
+                transform: translate(-50%, -50%);
+                padding: 0.8em 1.6em;
+                font: 1.2em sans-serif;
+                cursor: pointer;
+            }
+        "#;
+
+/// Run the example once the overlay is clicked, or activated with the keyboard.
+const RUN_SCRIPT: &str = "
+            const overlay = document.getElementById('run');
+            overlay.addEventListener('click', () => {
+                overlay.remove();
+                run();
+            }, { once: true });
+";
+
+/// The collapsible block with the command running an example locally, in a corner below it.
+const RUN_LOCALLY_STYLE: &str = r#"
+            details.run-locally {
+                position: fixed;
+                bottom: 0.5em;
+                left: 0.5em;
+                padding: 0.3em 0.6em;
+                background: #f6f8fa;
+                font: 0.9em sans-serif;
+            }
+            details.run-locally pre {
+                margin: 0.3em 0 0;
+            }
+        "#;
+
+/// The credit of the author of an example, in a corner of its page.
+const AUTHOR_STYLE: &str = r#"
+            p.author {
+                position: fixed;
+                bottom: 0.5em;
+                right: 0.5em;
+                margin: 0;
+                font: 0.8em sans-serif;
+                color: #57606a;
+            }
+        "#;
+
+/// The collapsible QR code linking to an example, at the bottom of its page.
+const QR_STYLE: &str = r#"
+            details.qr {
+                position: fixed;
+                bottom: 0.5em;
+                left: 50%;
+                transform: translateX(-50%);
+                padding: 0.3em 0.6em;
+                background: #f6f8fa;
+                font: 0.9em sans-serif;
+            }
+            details.qr img {
+                display: block;
+                width: 12em;
+                height: 12em;
+                margin: 0.3em auto 0;
+            }
+        "#;
+
+/// The navigation bar at the top of the example pages. The page takes the remaining height, so
+/// the bar doesn't cover the canvas, and the badge in the corner moves below it.
+const NAV_BAR_STYLE: &str = r#"
+            body {
+                display: flex;
+                flex-direction: column;
+            }
+            header.nav-bar {
+                flex: none;
+                display: flex;
+                align-items: center;
+                gap: 1em;
+                height: 2.5em;
+                padding: 0 1em;
+                box-sizing: border-box;
+                background: #24292f;
+                color: #f6f8fa;
+                font: 0.9em sans-serif;
+            }
+            header.nav-bar a {
+                color: inherit;
+            }
+            header.nav-bar .home {
+                flex: 1;
+                min-width: 0;
+                overflow: hidden;
+                white-space: nowrap;
+                text-overflow: ellipsis;
+                font-weight: bold;
+                text-decoration: none;
+            }
+            header.nav-bar details {
+                position: relative;
+            }
+            header.nav-bar summary {
+                cursor: pointer;
+            }
+            header.nav-bar ul {
+                position: absolute;
+                right: 0;
+                z-index: 1;
+                min-width: 12em;
+                max-height: 70vh;
+                overflow-y: auto;
+                margin: 0.5em 0 0;
+                padding: 0.3em 0;
+                list-style: none;
+                background: #24292f;
+            }
+            header.nav-bar li a {
+                display: block;
+                padding: 0.3em 1em;
+                white-space: nowrap;
+            }
+            header.nav-bar [aria-current] {
+                font-weight: bold;
+            }
+            .status {
+                top: 3em;
+            }
+            @media (max-width: 30em) {
+                header.nav-bar {
+                    gap: 0.5em;
+                    padding: 0 0.5em;
+                }
+                header.nav-bar ul {
+                    position: fixed;
+                    left: 0;
+                    right: 0;
+                    top: 2.5em;
+                    margin: 0;
+                }
+            }
+            @media print {
+                header.nav-bar {
+                    display: none;
+                }
+            }
+        "#;
+
+/// The canvas of the default layout shares the height of the page with the navigation bar.
+const NAV_BAR_CANVAS_STYLE: &str = r#"
+            canvas {
+                flex: 1 1 0;
+                height: auto;
+                min-height: 0;
+            }
+        "#;
+
+/// With `RCCHAT_NAV_BAR`, the navigation bar of the page of `example`, linking to the index and,
+/// in a menu, to the pages of the listed `examples` in the order of the index.
+fn nav_bar(example: &Example, examples: &[Example], config: &Config) -> String {
+    let root = root(config, config.pages_root());
+    let mut listed: Vec<&Example> = examples
+        .iter()
+        .filter(|e| !e.meta.unlisted || e.page == example.page)
+        .collect();
+    config.sort.sort(&mut listed);
+    let links: String = listed
+        .iter()
+        .map(|e| {
+            let current = if e.page == example.page {
+                " aria-current=\"page\""
+            } else {
+                ""
+            };
+            format!(
+                "\n                    <li><a href=\"{}{}\"{}>{}</a></li>",
+                root,
+                config.page_url(&e.page),
+                current,
+                e.module_path()
+            )
+        })
+        .collect();
+    format!(
+        r#"
+        <header class="nav-bar">
+            <a class="home" href="{root}index.{ext}">Druid WASM examples</a>
+            <details>
+                <summary>Examples</summary>
+                <ul>{links}
+                </ul>
+            </details>
+        </header>"#,
+        root = root,
+        ext = config.markup.extension(),
+        links = links,
+    )
+}
+
+/// With `RCCHAT_CANONICAL`, the link to the absolute url of the page of `example`, so that search
+/// engines index it once however it is reached.
+fn canonical_link(example: &Example, config: &Config) -> String {
+    match &config.base_url {
+        Some(base_url) if config.canonical => format!(
+            "\n        {}",
+            config.markup.void(&format!(
+                "link rel=\"canonical\" href=\"{}\"",
+                escape(&format!("{}/{}", base_url, config.page_url(&example.page)))
+            ))
+        ),
+        _ => String::new(),
+    }
+}
+
+/// With `RCCHAT_QR_CODES`, the QR code encoding the absolute url of the page of `example`, which
+/// is also downloadable.
+fn qr_code(example: &Example, config: &Config) -> Option<String> {
+    if !config.qr_codes {
+        return None;
+    }
+    let url = format!(
+        "{}/{}",
+        config.base_url.as_ref()?,
+        config.page_url(&example.page)
+    );
+    let code = match qr::encode(url.as_bytes()) {
+        Some(code) => code,
+        None => {
+            crate::warn(&format!("{} is too long for a QR code, skipping it", url));
+            return None;
+        }
+    };
+    let image = escape(&svg_data_url(&code.svg()));
+    Some(format!(
+        "\n        <details class=\"qr\"><summary>QR code</summary><a href=\"{image}\" download=\"{page}-qr.svg\">{img}</a></details>",
+        image = image,
+        page = escape(&example.page),
+        img = config.markup.void(&format!(
+            "img src=\"{}\" alt=\"QR code linking to {}\"",
+            image,
+            escape(&url)
+        ))
+    ))
+}
+
+/// The block showing `template` for running `example` locally.
+fn run_locally(example: &Example, template: &str) -> String {
+    format!(
+        "\n        <details class=\"run-locally\"><summary>Run locally</summary><pre><code>{}</code></pre></details>",
+        escape(&template.replace("{name}", &example.stem))
+    )
+}
+
+/// Render the page of `example`, one of `examples`.
+pub fn example_page(example: &Example, examples: &[Example], config: &Config) -> String {
+    let markup = config.markup;
+    let style = if example.meta.non_graphical {
+        r#"
+            html, body {
+                margin: 0px;
+                padding: 0px;
+            }
+            .output {
+                margin: 0px;
+                padding: 1em;
+                min-height: 50vh;
+                white-space: pre-wrap;
+            }
+            nav {
+                padding: 0.5em 1em;
+            }
+        "#
+    } else if config.embed_source {
+        r#"
+            html, body {
+                margin: 0px;
+                padding: 0px;
+            }
+            canvas {
+                display: block;
+                width: 100%;
+                height: 70vh;
+            }
+            nav {
+                padding: 0.5em 1em;
+            }
+        "#
+    } else {
+        r#"
+            html, body, canvas {
+                margin: 0px;
+                padding: 0px;
+                width: 100%;
+                height: 100%;
+                overflow: hidden;
+            }
+        "#
+    };
+    let (load, init, call) = match config.script_mode {
+        ScriptMode::Module => (
+            format!(
+                "\n            import init, {{ {} }} from '{}/druid_wasm_examples.js';\n",
+                example.entry,
+                pkg_url(config)
+            ),
+            "init()".to_string(),
+            example.entry.clone(),
+        ),
+        ScriptMode::Classic => (
+            String::new(),
+            format!(
+                "wasm_bindgen('{}/druid_wasm_examples_bg.wasm')",
+                pkg_url(config)
+            ),
+            format!("wasm_bindgen.{}", example.entry),
+        ),
+    };
+    let script = format!(
+        r#"{load}
+            async function run() {{{checks}{before}
+                await {init};
+                {call}({argument});{after}
+            }}
+{start}        "#,
+        start = if config.lazy_init {
+            RUN_SCRIPT
+        } else {
+            "\n            run();\n"
+        },
+        load = load,
+        init = init,
+        call = call,
+        argument = example
+            .meta
+            .argument
+            .as_ref()
+            .map_or(String::new(), Json::to_string),
+        checks = hook(Some(&support_checks(
+            &config.no_wasm_message,
+            &example.meta.requires
+        ))),
+        before = hook(config.before_init.as_deref()),
+        after = hook(config.after_init.as_deref()),
+    );
+
+    // The source is shown below the example, with a link back to the index above it unless the
+    // navigation bar links there.
+    let (stylesheet, nav, source) = if config.embed_source {
+        (
+            format!(
+                "\n        {}",
+                markup.void(&format!(
+                    "link rel=\"stylesheet\" href=\"{}style.css\"",
+                    root(config, config.pages_root())
+                ))
+            ),
+            if config.nav_bar {
+                String::new()
+            } else {
+                format!(
+                    "\n        <nav><a href=\"{}index.{}\">&#8592; All examples</a></nav>",
+                    root(config, config.pages_root()),
+                    markup.extension()
+                )
+            },
+            format!(
+                "\n        <pre class=\"source\"><code>{}</code></pre>",
+                highlight(&example.source)
+            ),
+        )
+    } else {
+        (String::new(), String::new(), String::new())
+    };
+
+    // With lazy initialization, an overlay asks to run the example.
+    let (run_style, overlay) = if config.lazy_init {
+        (
+            format!(
+                "\n        <style>{}</style>",
+                markup.raw_text(RUN_STYLE, ("/*", "*/"))
+            ),
+            "\n        <button type=\"button\" id=\"run\" class=\"run\">Click to run</button>",
+        )
+    } else {
+        (String::new(), "")
+    };
+
+    let (run_locally_style, run_locally) = match &config.run_command {
+        Some(template) => (
+            format!(
+                "\n        <style>{}</style>",
+                markup.raw_text(RUN_LOCALLY_STYLE, ("/*", "*/"))
+            ),
+            run_locally(example, template),
+        ),
+        None => (String::new(), String::new()),
+    };
+
+    // The author is credited in the opposite corner.
+    let (author_style, author) = match &example.meta.author {
+        Some(author) => (
+            format!(
+                "\n        <style>{}</style>",
+                markup.raw_text(AUTHOR_STYLE, ("/*", "*/"))
+            ),
+            format!("\n        <p class=\"author\">By {}</p>", escape(author)),
+        ),
+        None => (String::new(), String::new()),
+    };
+
+    // The background is set on the root, so that it shows around the canvas and behind the page.
+    let background_style = match &example.meta.background {
+        Some(background) => format!(
+            "\n        <style>{}</style>",
+            markup.raw_text(
+                &format!(
+                    "\n            html {{ background: {}; }}\n        ",
+                    background.css()
+                ),
+                ("/*", "*/")
+            )
+        ),
+        None => String::new(),
+    };
+
+    let (nav_bar_style, nav_bar) = if config.nav_bar {
+        let mut style = NAV_BAR_STYLE.to_string();
+        if !example.meta.non_graphical && !config.embed_source {
+            style.push_str(NAV_BAR_CANVAS_STYLE.trim_start_matches('\n'));
+        }
+        (
+            format!(
+                "\n        <style>{}</style>",
+                markup.raw_text(&style, ("/*", "*/"))
+            ),
+            nav_bar(example, examples, config),
+        )
+    } else {
+        (String::new(), String::new())
+    };
+
+    let (qr_style, qr) = match qr_code(example, config) {
+        Some(qr) => (
+            format!(
+                "\n        <style>{}</style>",
+                markup.raw_text(QR_STYLE, ("/*", "*/"))
+            ),
+            qr,
+        ),
+        None => (String::new(), String::new()),
+    };
+
+    // The status badge floats in a corner, above the example.
+    let (badge_style, status) = match example.meta.status {
+        Some(status) => (
+            format!(
+                "\n        <style>{}</style>",
+                markup.raw_text(
+                    &format!(
+                        "{}            .status {{ position: fixed; top: 0.5em; right: 0.5em; }}\n        ",
+                        BADGE_STYLE.trim_end_matches(' ')
+                    ),
+                    ("/*", "*/")
+                )
+            ),
+            format!(
+                "\n        <div class=\"status\">{}</div>",
+                badge(status).trim()
+            ),
+        ),
+        None => (String::new(), String::new()),
+    };
+
+    let page = format!(
+        r#"{start}
+    <head>
+        {meta}
+        <title>Druid WASM examples - {name}</title>{description}{canonical}{stylesheet}{preload}
+        <style>{style}</style>{background_style}{badge_style}{author_style}{run_style}{run_locally_style}{qr_style}{nav_bar_style}
+    </head>
+    <body>
+        <noscript>This page contains webassembly and javascript content, please enable javascript in your browser.</noscript>{nav_bar}{nav}{status}{author}
+        {body}{overlay}{run_locally}{qr}{source}{output_script}
+        {script}{body_end}
+    </body>
+</html>"#,
+        start = markup.document_start(config),
+        meta = head_meta(
+            config,
+            example
+                .meta
+                .theme_color
+                .as_deref()
+                .or(config.theme_color.as_deref()),
+            &root(config, config.pages_root())
+        ),
+        name = escape(example.meta.title.as_deref().unwrap_or(&example.entry)),
+        description = match &example.meta.description {
+            Some(description) => format!(
+                "\n        {}",
+                markup.void(&format!(
+                    "meta name=\"description\" content=\"{}\"",
+                    escape(description)
+                ))
+            ),
+            None => String::new(),
+        },
+        canonical = canonical_link(example, config),
+        style = markup.raw_text(style, ("/*", "*/")),
+        stylesheet = stylesheet,
+        preload = preload_links(config),
+        background_style = background_style,
+        badge_style = badge_style,
+        author_style = author_style,
+        author = author,
+        run_style = run_style,
+        run_locally_style = run_locally_style,
+        overlay = overlay,
+        run_locally = run_locally,
+        qr_style = qr_style,
+        qr = qr,
+        nav_bar_style = nav_bar_style,
+        nav_bar = nav_bar,
+        nav = nav,
+        status = status,
+        body = match &example.meta.body {
+            Some(body) => body,
+            None if example.meta.non_graphical => OUTPUT_BODY,
+            None => DEFAULT_BODY,
+        },
+        output_script = if example.meta.non_graphical {
+            format!(
+                "\n        <script>{}</script>",
+                markup.raw_text(OUTPUT_SCRIPT, ("//", "\n"))
+            )
+        } else {
+            String::new()
+        },
+        source = source,
+        script = match config.script_mode {
+            ScriptMode::Module => format!(
+                "<script type=\"module\">{}</script>",
+                markup.raw_text(&script, ("//", "\n"))
+            ),
+            ScriptMode::Classic => format!(
+                "<script src=\"{}/druid_wasm_examples.js\"></script>\n        <script>{}</script>",
+                escape(&pkg_url(config)),
+                markup.raw_text(&script, ("//", "\n"))
+            ),
+        },
+        body_end = body_end(config, &root(config, config.pages_root())),
+    );
+    with_csp(page, config)
+}
+
+/// With `RCCHAT_PRELOAD`, links letting the browser fetch the bundle while it parses the page. They
+/// need the same urls and cors mode as the script loading the bundle, or it is fetched twice.
+fn preload_links(config: &Config) -> String {
+    if !config.preload {
+        return String::new();
+    }
+    let markup = config.markup;
+    let pkg = escape(&pkg_url(config));
+    let js = match config.script_mode {
+        ScriptMode::Module => format!(
+            "link rel=\"modulepreload\" href=\"{}/druid_wasm_examples.js\"",
+            pkg
+        ),
+        ScriptMode::Classic => format!(
+            "link rel=\"preload\" href=\"{}/druid_wasm_examples.js\" as=\"script\"",
+            pkg
+        ),
+    };
+    format!(
+        "\n        {}\n        {}",
+        markup.void(&js),
+        markup.void(&format!(
+            concat!(
+                "link rel=\"preload\" href=\"{}/druid_wasm_examples_bg.wasm\" as=\"fetch\" ",
+                "type=\"application/wasm\" crossorigin=\"anonymous\""
+            ),
+            pkg
+        ))
+    )
+}
+
+/// The `<meta>` tags shared by all pages, which have to come first in their head, followed by the
+/// links to the web app manifest and icon. `root` is the url of the directory of the index.
+fn head_meta(config: &Config, theme_color: Option<&str>, root: &str) -> String {
+    let markup = config.markup;
+    let mut meta = format!(
+        "{}\n        {}",
+        markup.void(&format!("meta charset=\"{}\"", config.charset)),
+        markup.void("meta name=\"viewport\" content=\"width=device-width, initial-scale=1\"")
+    );
+    if let Some(policy) = &config.cache_control {
+        let mut headers = vec![("Cache-Control", policy.as_str())];
+        // HTTP/1.0 caches only know these.
+        if policy.contains("no-cache") || policy.contains("no-store") {
+            headers.push(("Pragma", "no-cache"));
+            headers.push(("Expires", "0"));
+        }
+        for (header, value) in headers {
+            meta.push_str("\n        ");
+            meta.push_str(&markup.void(&format!(
+                "meta http-equiv=\"{}\" content=\"{}\"",
+                header, value
+            )));
+        }
+    }
+    if let Some(color) = theme_color {
+        meta.push_str("\n        ");
+        meta.push_str(&markup.void(&format!("meta name=\"theme-color\" content=\"{}\"", color)));
+    }
+    if config.manifest {
+        meta.push_str("\n        ");
+        meta.push_str(&markup.void(&format!(
+            "link rel=\"manifest\" href=\"{}manifest.webmanifest\"",
+            root
+        )));
+        if let Some(icon) = touch_icon(&config.icons) {
+            meta.push_str("\n        ");
+            meta.push_str(&markup.void(&format!(
+                "link rel=\"apple-touch-icon\" href=\"{}{}\"",
+                root,
+                escape(&icon.file)
+            )));
+        }
+    }
+    meta
+}
+
+/// The url of the directory of the index from a page, which is `relative`, or absolute under the
+/// base url with `RCCHAT_ABSOLUTE_URLS`.
+fn root(config: &Config, relative: &str) -> String {
+    match &config.base_url {
+        Some(base_url) if config.absolute_urls => format!("{}/", base_url),
+        _ => relative.to_string(),
+    }
+}
+
+/// The url of the directory of the wasm bundle, as imported by the example pages. With
+/// `RCCHAT_ABSOLUTE_URLS` a relative `RCCHAT_PKG_BASE` is resolved against the url of the pages.
+fn pkg_url(config: &Config) -> String {
+    let base_url = match &config.base_url {
+        Some(base_url) if config.absolute_urls => base_url,
+        _ => return config.pkg_base.clone(),
+    };
+    let pkg_base = &config.pkg_base;
+    let scheme_end = base_url.find("://").unwrap() + 3;
+    if pkg_base.contains("://") {
+        return pkg_base.clone();
+    } else if pkg_base.starts_with("//") {
+        return format!("{}{}", &base_url[..scheme_end - 2], pkg_base);
+    }
+    let origin_end = base_url[scheme_end..]
+        .find('/')
+        .map_or(base_url.len(), |i| scheme_end + i);
+    let mut segments: Vec<&str> = if pkg_base.starts_with('/') {
+        Vec::new()
+    } else {
+        base_url[origin_end..]
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .chain(Some("html").filter(|_| !config.flat_pages))
+            .collect()
+    };
+    for segment in pkg_base.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    let mut url = base_url[..origin_end].to_string();
+    for segment in segments {
+        url.push('/');
+        url.push_str(segment);
+    }
+    url
+}
+
+/// Declare the content security policy in the head of the page `doc`, if enabled. The `{hashes}`
+/// in the policy are replaced by the hashes of the inline scripts of the page.
+fn with_csp(doc: String, config: &Config) -> String {
+    let policy = match &config.csp {
+        Some(policy) => policy,
+        None => return doc,
+    };
+    let hashes: Vec<String> = inline_scripts(&doc)
+        .iter()
+        .map(|script| format!("'sha256-{}'", base64(&sha256(script.as_bytes()))))
+        .collect();
+    let policy = policy.replace("{hashes}", &hashes.join(" "));
+    let meta = config.markup.void(&format!(
+        "meta http-equiv=\"Content-Security-Policy\" content=\"{}\"",
+        // Quotes are common in policies, and only need escaping in attributes using them.
+        policy
+            .trim()
+            .replace('&', "&amp;")
+            .replace('"', "&quot;")
+            .replace('<', "&lt;")
+    ));
+    // The policy only applies to the scripts following it, which all come after the title.
+    let at = doc.find("\n        <title>").unwrap_or(0);
+    format!("{}\n        {}{}", &doc[..at], meta, &doc[at..])
+}
+
+/// The text of the inline scripts of `doc`, as hashed by browsers. JSON-LD isn't run, so it is
+/// left out.
+fn inline_scripts(doc: &str) -> Vec<String> {
+    let mut scripts = Vec::new();
+    let mut rest = doc;
+    while let Some(start) = rest.find("<script") {
+        rest = &rest[start..];
+        let tag_end = match rest.find('>') {
+            Some(end) => end,
+            None => break,
+        };
+        let tag = &rest[..tag_end];
+        rest = &rest[tag_end + 1..];
+        let end = rest.find("</script>").unwrap_or(rest.len());
+        if !tag.contains(" src=") && !tag.contains("application/ld+json") {
+            // The CDATA markers of XHTML pages aren't part of the text of the script.
+            scripts.push(rest[..end].replace("<![CDATA[", "").replace("]]>", ""));
+        }
+        rest = &rest[end..];
+    }
+    scripts
+}
+
+/// Markup shared by all pages, inserted at the end of their body. `root` is the url of the
+/// directory of the index from the page.
+fn body_end(config: &Config, root: &str) -> String {
+    let mut html = String::new();
+    if let Some(banner) = &config.banner {
+        html.push_str("\n        ");
+        html.push_str(banner.trim());
+    }
+    if config.service_worker {
+        let register = format!(
+            "\n            if ('serviceWorker' in navigator) {{\n                navigator.serviceWorker.register('{}sw.js');\n            }}\n        ",
+            root
+        );
+        html.push_str(&format!(
+            "\n        <script>{}</script>",
+            config.markup.raw_text(&register, ("//", "\n"))
+        ));
+    }
+    html
+}
+
+/// A JavaScript expression telling whether the browser supports `feature`, which may `await`.
+fn feature_check(feature: Feature) -> &'static str {
+    match feature {
+        Feature::WebGl => "document.createElement('canvas').getContext('webgl')",
+        Feature::WebGl2 => "document.createElement('canvas').getContext('webgl2')",
+        Feature::WebGpu => "navigator.gpu && await navigator.gpu.requestAdapter()",
+    }
+}
+
+/// Checks run before initializing an example, which show a message instead of the canvas if the
+/// browser lacks WebAssembly, showing `no_wasm`, or one of the `features` the example requires.
+fn support_checks(no_wasm: &str, features: &[Feature]) -> String {
+    let mut checks = String::from(concat!(
+        "const unsupported = text => {\n",
+        "    const message = document.createElement('p');\n",
+        "    message.style.padding = '1em';\n",
+        "    message.textContent = text;\n",
+        "    const canvas = document.getElementById('canvas');\n",
+        "    canvas ? canvas.replaceWith(message) : document.body.prepend(message);\n",
+        "};\n",
+    ));
+    checks.push_str(&format!(
+        "if (typeof WebAssembly === 'undefined') {{\n    return unsupported({});\n}}\n",
+        Json::String(no_wasm.to_string())
+    ));
+    for &feature in features {
+        checks.push_str(&format!(
+            "if (!({})) {{\n    return unsupported('This example requires {}, which is not available in this browser.');\n}}\n",
+            feature_check(feature),
+            feature.label()
+        ));
+    }
+    checks
+}
+
+/// Indent a JavaScript snippet to be spliced into the body of `run()`.
+fn hook(snippet: Option<&str>) -> String {
+    let mut indented = String::new();
+    for line in snippet.unwrap_or_default().lines() {
+        indented.push('\n');
+        if !line.trim().is_empty() {
+            indented.push_str("                ");
+            indented.push_str(line);
+        }
+    }
+    indented
+}
+
+/// Check that the bundle `js`, as generated by wasm-pack, can be loaded by pages in `mode`.
+pub fn check_bundle(js: &str, mode: ScriptMode) -> Result<(), String> {
+    let module = js.contains("export default");
+    let expected = match mode {
+        ScriptMode::Module => module,
+        ScriptMode::Classic => !module && js.contains("wasm_bindgen"),
+    };
+    if expected {
+        Ok(())
+    } else {
+        Err(format!(
+            "the bundle wasn't built for {} scripts, build it with `wasm-pack build --target {}`",
+            match mode {
+                ScriptMode::Module => "module",
+                ScriptMode::Classic => "classic",
+            },
+            mode.target()
+        ))
+    }
+}
+
+/// Check that the example page `doc` loads the bundle in `mode` and calls the entry function
+/// `entry`.
+///
+/// This guards against the template and the computed entry names getting out of sync.
+pub fn check_entry(doc: &str, entry: &str, mode: ScriptMode) -> Result<(), String> {
+    let (start, call) = match mode {
+        ScriptMode::Module => {
+            const IMPORT: &str = "import init, { ";
+            let start = doc
+                .find(IMPORT)
+                .ok_or_else(|| "the page doesn't import the bundle".to_string())?
+                + IMPORT.len();
+            let imported = doc[start..].split(" }").next().unwrap_or_default().trim();
+            if imported != entry {
+                return Err(format!(
+                    "the page imports `{}` instead of `{}`",
+                    imported, entry
+                ));
+            }
+            (start, format!("{}(", entry))
+        }
+        ScriptMode::Classic => {
+            const LOAD: &str = "/druid_wasm_examples.js\"></script>";
+            let start = doc
+                .find(LOAD)
+                .ok_or_else(|| "the page doesn't load the bundle".to_string())?;
+            (start, format!("wasm_bindgen.{}(", entry))
+        }
+    };
+    let calls = |line: &str| line.starts_with(&call) && line.ends_with(");");
+    if !doc[start..].lines().any(|line| calls(line.trim())) {
+        return Err(format!("the page doesn't call `{}`", entry));
+    }
+    Ok(())
+}
+
+/// Check that `doc` is well-formed XML: every element is closed in the order it was opened, and
+/// every `&` starts a character reference or one of the entities XML predefines.
+///
+/// This is not a full XML parser, but it catches the mistakes likely to come from hand-written
+/// fragments, such as unclosed void elements.
+pub fn check_well_formed(doc: &str) -> Result<(), String> {
+    let mut open: Vec<&str> = Vec::new();
+    let mut rest = doc;
+    while let Some(start) = rest.find('<') {
+        check_references(&rest[..start])?;
+        rest = &rest[start..];
+        // Skip markup which doesn't open or close elements.
+        let skip = [
+            ("<?", "?>"),
+            ("<!--", "-->"),
+            ("<![CDATA[", "]]>"),
+            ("<!", ">"),
+        ]
+        .iter()
+        .find(|(open, _)| rest.starts_with(open));
+        if let Some((_, close)) = skip {
+            let end = rest.find(close).ok_or_else(|| {
+                format!(
+                    "unterminated `{}`",
+                    rest.chars().take(20).collect::<String>()
+                )
+            })?;
+            rest = &rest[end + close.len()..];
+            continue;
+        }
+
+        let end = tag_end(rest).ok_or_else(|| "unterminated tag".to_string())?;
+        let tag = &rest[1..end];
+        check_references(tag)?;
+        rest = &rest[end + 1..];
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim();
+            match open.pop() {
+                Some(expected) if expected == name => (),
+                Some(expected) => {
+                    return Err(format!("found </{}> while <{}> is open", name, expected))
+                }
+                None => return Err(format!("found </{}> without an open element", name)),
+            }
+        } else if !tag.ends_with('/') {
+            let name = tag.split_whitespace().next().unwrap_or_default();
+            open.push(name);
+            // The contents of scripts and styles are text, skip to their closing tag.
+            if name == "script" || name == "style" {
+                let close = format!("</{}", name);
+                let end = rest
+                    .find(&close)
+                    .ok_or_else(|| format!("<{}> is never closed", name))?;
+                rest = &rest[end..];
+            }
+        }
+    }
+    check_references(rest)?;
+    match open.pop() {
+        Some(name) => Err(format!("<{}> is never closed", name)),
+        None => Ok(()),
+    }
+}
+
+/// The index of the `>` ending the tag at the start of `markup`, skipping those in quoted
+/// attribute values.
+fn tag_end(markup: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in markup.char_indices() {
+        match (quote, c) {
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, '>') => return Some(i),
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Check that each `&` in `text` starts a character or entity reference XML knows without a DTD.
+fn check_references(text: &str) -> Result<(), String> {
+    for (i, _) in text.match_indices('&') {
+        let reference = text[i + 1..].split(';').next().unwrap_or_default();
+        let valid = text[i + 1..].contains(';')
+            && match reference.strip_prefix('#') {
+                Some(code) => {
+                    let code = match code.strip_prefix('x') {
+                        Some(hex) => u32::from_str_radix(hex, 16),
+                        None => code.parse(),
+                    };
+                    code.ok().and_then(std::char::from_u32).is_some()
+                }
+                None => ["amp", "lt", "gt", "quot", "apos"].contains(&reference),
+            };
+        if !valid {
+            let context: String = text[i..].chars().take(12).collect();
+            return Err(format!(
+                "`{}` is not a valid reference, escape `&` as `&amp;`",
+                context
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discover::{discover, Root};
+    use std::fs;
+
+    const PAGE: &str = r#"
+        <script type="module">
+            import init, { hello } from '../pkg/druid_wasm_examples.js';
+
+            async function run() {
+                await init();
+                hello();
+            }
+
+            run();
+        </script>"#;
+
+    #[test]
+    fn checks_the_entry_of_pages() {
+        assert_eq!(check_entry(PAGE, "hello", ScriptMode::Module), Ok(()));
+
+        let err = check_entry(PAGE, "calc", ScriptMode::Module).unwrap_err();
+        assert_eq!(err, "the page imports `hello` instead of `calc`");
+
+        let tampered = PAGE.replace("hello();", "hello_world();");
+        let err = check_entry(&tampered, "hello", ScriptMode::Module).unwrap_err();
+        assert_eq!(err, "the page doesn't call `hello`");
+
+        let with_argument = PAGE.replace("hello();", r#"hello({"id":"canvas"});"#);
+        assert_eq!(
+            check_entry(&with_argument, "hello", ScriptMode::Module),
+            Ok(())
+        );
+
+        let err = check_entry(PAGE, "hello", ScriptMode::Classic).unwrap_err();
+        assert_eq!(err, "the page doesn't load the bundle");
+    }
+
+    #[test]
+    fn checks_the_target_of_the_bundle() {
+        let web = "export function hello() {}\nexport default __wbg_init;";
+        let no_modules =
+            "let wasm_bindgen;\n(function() { wasm_bindgen = Object.assign(__wbg_init, {}); })();";
+        assert_eq!(check_bundle(web, ScriptMode::Module), Ok(()));
+        assert_eq!(check_bundle(no_modules, ScriptMode::Classic), Ok(()));
+        let err = check_bundle(web, ScriptMode::Classic).unwrap_err();
+        assert!(err.contains("--target no-modules"), "{}", err);
+        assert!(check_bundle(no_modules, ScriptMode::Module).is_err());
+    }
+
+    #[test]
+    fn checks_documents_are_well_formed() {
+        let ok = |doc| check_well_formed(doc).map_err(|e| format!("{}: {}", doc, e));
+        let err = |doc| check_well_formed(doc).unwrap_err();
+        assert_eq!(
+            ok(concat!(
+                "<?xml version=\"1.0\"?>\n<!DOCTYPE html>\n<html><!-- <p> -->",
+                "<head><meta charset=\"utf-8\"/><style>a > b { }</style></head>",
+                "<body><p title=\"a > b\" data-x='<'>x &amp; y &#8592; &#x2192; &lt;</p>",
+                "<br/><img src=\"a.png\" alt=\"\" /><script><![CDATA[ if (a < b && c) {} ]]></script>",
+                "</body></html>",
+            )),
+            Ok(())
+        );
+
+        assert_eq!(err("<p><b>bold</p></b>"), "found </p> while <b> is open");
+        assert_eq!(
+            err("<p>text</p></div>"),
+            "found </div> without an open element"
+        );
+        assert_eq!(err("<body><br></body>"), "found </body> while <br> is open");
+        assert_eq!(err("<html><body>"), "<body> is never closed");
+        assert_eq!(err("<p title=\"a > b\""), "unterminated tag");
+        assert_eq!(
+            err("<p><!-- never closed </p>"),
+            "unterminated `<!-- never closed </`"
+        );
+        assert_eq!(err("<script>let a = 1;"), "<script> is never closed");
+
+        assert_eq!(
+            err("<p>Tom & Jerry</p>"),
+            "`& Jerry` is not a valid reference, escape `&` as `&amp;`"
+        );
+        assert!(err("<p>&nbsp;</p>").starts_with("`&nbsp;` is not a valid reference"));
+        assert!(err("<a href=\"?a=1&b=2\">x</a>").contains("`&b=2\"` is not"));
+        assert!(err("<p>&#xD800;</p>").contains("not a valid reference"));
+        assert!(err("<p>trailing &amp</p>").contains("not a valid reference"));
+    }
+
+    /// Discover examples with the given stems and sources in the fixture directory `name`, along
+    /// with the default configuration. The directory is removed when the first value is dropped.
+    fn fixture(name: &str, sources: &[(&str, &str)]) -> (crate::FixtureDir, Vec<Example>, Config) {
+        let dir = crate::fixture_dir(name);
+        let examples_dir = dir.join("examples");
+        fs::create_dir_all(&examples_dir).unwrap();
+        for (stem, source) in sources {
+            fs::write(examples_dir.join(format!("{}.rs", stem)), source).unwrap();
+        }
+        let roots = [Root {
+            name: None,
+            dir: examples_dir,
+        }];
+        let examples = discover(&roots, "_", false).unwrap().examples;
+        let config = Config::from_env(&dir);
+        (dir, examples, config)
+    }
+
+    #[test]
+    fn paginates_the_index() {
+        let stems = ["a", "b", "c", "d", "e", "f"];
+        let mut sources: Vec<(&str, &str)> = stems.iter().map(|stem| (*stem, "")).collect();
+        sources.push(("hidden", "//! ```toml\n//! unlisted = true\n//! ```\n"));
+        let (_dir, examples, mut config) = fixture("paginate", &sources);
+        let skipped = ["svg".to_string()];
+        let listed = |page: &str| -> Vec<String> {
+            page.split("<li id=\"")
+                .skip(1)
+                .map(|entry| entry.split('"').next().unwrap().to_string())
+                .collect()
+        };
+
+        // Without a page size, and with one the examples fit in, the index is a single page.
+        for size in [None, Some(6), Some(100)] {
+            config.index_page_size = size;
+            let pages = index_pages(&examples, &skipped, &config);
+            assert_eq!(pages.len(), 1);
+            assert_eq!(pages[0].0, "index.html");
+            assert_eq!(listed(&pages[0].1), ["a", "b", "c", "d", "e", "f", "svg"]);
+            assert!(!pages[0].1.contains("class=\"pages\""));
+        }
+
+        // An exact multiple of the page size fills the last page, without an empty one after it.
+        config.index_page_size = Some(3);
+        let pages = index_pages(&examples, &skipped, &config);
+        let files: Vec<&str> = pages.iter().map(|(file, _)| file.as_str()).collect();
+        assert_eq!(files, ["index.html", "index-2.html"]);
+        assert_eq!(listed(&pages[0].1), ["a", "b", "c"]);
+        assert_eq!(listed(&pages[1].1), ["d", "e", "f", "svg"]);
+        assert!(pages[0].1.contains(concat!(
+            "<span aria-current=\"page\">1</span><a href=\"./index-2.html\">2</a>",
+            "<a href=\"./index-2.html\">Next &#8594;</a></nav>"
+        )));
+        assert!(pages[1].1.contains(concat!(
+            "<a href=\"./index.html\">&#8592; Previous</a><a href=\"./index.html\">1</a>",
+            "<span aria-current=\"page\">2</span></nav>"
+        )));
+        assert!(pages[1]
+            .1
+            .contains("<title>Druid WASM examples - index, page 2 of 2</title>"));
+
+        // One more than a multiple starts another page.
+        config.index_page_size = Some(5);
+        let pages = index_pages(&examples, &skipped, &config);
+        assert_eq!(pages.len(), 2);
+        assert_eq!(listed(&pages[1].1), ["f", "svg"]);
+        config.index_page_size = Some(1);
+        assert_eq!(index_pages(&examples, &skipped, &config).len(), 6);
+
+        // Without examples, there is still an index.
+        let pages = index_pages(&[], &[], &config);
+        assert_eq!(pages.len(), 1);
+        assert_eq!(listed(&pages[0].1), Vec::<String>::new());
+    }
+
+    #[test]
+    fn toggles_category_sections() {
+        let sources = [
+            ("flex", "//! category: Layout\n"),
+            ("hello", ""),
+            ("calc", "//! category: Input\n"),
+            ("split", "//! category: Layout\n"),
+            ("list", "//! category: events\n"),
+        ];
+        let (_dir, examples, mut config) = fixture("categories", &sources);
+        let index = |config: &Config| index_pages(&examples, &[], config).remove(0).1;
+
+        assert!(!index(&config).contains("<details"));
+
+        config.category_sections = Some(true);
+        let page = index(&config);
+        let sections: Vec<&str> = page.split("<details").skip(1).collect();
+        assert_eq!(sections.len(), 4, "{}", page);
+        for section in &sections {
+            assert!(
+                section.starts_with(" class=\"category\" open=\"open\">"),
+                "{}",
+                section
+            );
+        }
+        // Sorted by name ignoring case, with the examples without one last.
+        let names: Vec<&str> = sections
+            .iter()
+            .map(|s| {
+                s.split("<h2>")
+                    .nth(1)
+                    .unwrap()
+                    .split("</h2>")
+                    .next()
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(names, ["events", "Input", "Layout", "Other"]);
+        assert!(sections[2].contains(
+            "<li id=\"flex\"><a href=\"./html/flex.html\">flex</a></li><li id=\"split\">"
+        ));
+        assert!(sections[3].contains("<li id=\"hello\">"));
+        assert!(!page.contains(SECTION_TARGET_SCRIPT));
+
+        // Collapsed sections open when a link targets one of their examples.
+        config.category_sections = Some(false);
+        let page = index(&config);
+        assert_eq!(page.matches("<details class=\"category\">").count(), 4);
+        assert!(!page.contains("open=\"open\""));
+        assert!(page.contains(SECTION_TARGET_SCRIPT));
+    }
+
+    #[test]
+    fn links_alternate_languages() {
+        let (_dir, examples, mut config) = fixture("alternates", &[("hello", "")]);
+        let index = |config: &Config| index_pages(&examples, &[], config);
+        assert!(!index(&config)[0].1.contains("hreflang"));
+
+        config.locales = vec![
+            ("fr".to_string(), "https://example.com/fr".to_string()),
+            ("en".to_string(), "https://example.com/en".to_string()),
+        ];
+        let page = index(&config).remove(0).1;
+        let links: Vec<&str> = page
+            .lines()
+            .map(str::trim)
+            .filter(|line| line.contains("rel=\"alternate\""))
+            .collect();
+        assert_eq!(
+            links,
+            [
+                r#"<link rel="alternate" hreflang="fr" href="https://example.com/fr/index.html">"#,
+                r#"<link rel="alternate" hreflang="en" href="https://example.com/en/index.html">"#,
+                r#"<link rel="alternate" hreflang="x-default" href="https://example.com/fr/index.html">"#,
+            ]
+        );
+
+        // Further pages of the index link their counterparts.
+        config.index_page_size = Some(1);
+        config.markup = Markup::Xhtml;
+        let (_dir, examples, _) = fixture("alternates_pages", &[("calc", ""), ("hello", "")]);
+        let pages = index_pages(&examples, &[], &config);
+        assert!(pages[1].1.contains(
+            r#"<link rel="alternate" hreflang="en" href="https://example.com/en/index-2.xhtml" />"#
+        ));
+    }
+
+    #[test]
+    fn imports_the_bundle_from_its_base() {
+        let (_dir, examples, mut config) = fixture("pkg_base", &[("calc", "")]);
+        let page = |config: &Config| example_page(&examples[0], &examples, config);
+        assert!(
+            page(&config).contains("import init, { calc } from '../pkg/druid_wasm_examples.js';")
+        );
+
+        config.pkg_base = "https://cdn.example.org/druid/0.1.0".to_string();
+        assert!(page(&config).contains(concat!(
+            "import init, { calc } from ",
+            "'https://cdn.example.org/druid/0.1.0/druid_wasm_examples.js';"
+        )));
+        config.script_mode = ScriptMode::Classic;
+        assert!(page(&config).contains(
+            "<script src=\"https://cdn.example.org/druid/0.1.0/druid_wasm_examples.js\"></script>"
+        ));
+
+        // With absolute urls, a relative base is resolved against the url of the pages.
+        config.base_url = Some("https://example.org/demo".to_string());
+        config.absolute_urls = true;
+        let resolve = |pkg_base: &str, config: &mut Config| {
+            config.pkg_base = pkg_base.to_string();
+            pkg_url(config)
+        };
+        assert_eq!(
+            resolve("../pkg", &mut config),
+            "https://example.org/demo/pkg"
+        );
+        assert_eq!(
+            resolve("./v1/../pkg/", &mut config),
+            "https://example.org/demo/html/pkg"
+        );
+        assert_eq!(
+            resolve("/static/pkg", &mut config),
+            "https://example.org/static/pkg"
+        );
+        assert_eq!(
+            resolve("//cdn.example.org/pkg", &mut config),
+            "https://cdn.example.org/pkg"
+        );
+        assert_eq!(
+            resolve("http://cdn.example.org/pkg", &mut config),
+            "http://cdn.example.org/pkg"
+        );
+        config.flat_pages = true;
+        assert_eq!(
+            resolve("./pkg", &mut config),
+            "https://example.org/demo/pkg"
+        );
+    }
+}
@@ -0,0 +1,129 @@
+// Copyright 2020 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Persistent ids of the examples, ordering them with `RCCHAT_SORT=id`.
+//!
+//! The ids are kept in a file committed along with the generated files, with a line `<id> <module
+//! path>` per example. New examples get the next free id, so they are listed last, and the lines
+//! of removed examples are dropped. An example keeps its place when renamed by renaming it in the
+//! file as well, or automatically when it is the only example removed and added since the last
+//! build.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The id file, relative to this crate.
+pub const FILE: &str = "example-ids.txt";
+
+const HEADER: &str = "# The order of the examples with RCCHAT_SORT=id, maintained by the build. \
+                      Rename the module\n# of a renamed example here to keep its place.\n";
+
+/// Parse the contents of the id file into module paths with their id.
+pub fn parse(src: &str) -> Result<BTreeMap<String, u64>, String> {
+    let mut ids = BTreeMap::new();
+    let mut seen = BTreeSet::new();
+    for (n, line) in src.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |msg: &str| format!("line {}: {}", n + 1, msg);
+        let mut parts = line.split_whitespace();
+        let id = parts.next().unwrap();
+        let id: u64 = id
+            .parse()
+            .map_err(|_| error(&format!("`{}` is not an id", id)))?;
+        let module = match (parts.next(), parts.next()) {
+            (Some(module), None) => module,
+            _ => return Err(error("expected an id followed by a module path")),
+        };
+        if !seen.insert(id) {
+            return Err(error(&format!("the id {} is given twice", id)));
+        }
+        if ids.insert(module.to_string(), id).is_some() {
+            return Err(error(&format!("`{}` is given twice", module)));
+        }
+    }
+    Ok(ids)
+}
+
+/// The ids of the examples with the module paths `modules`, keeping the ids of `previous`.
+pub fn assign(previous: &BTreeMap<String, u64>, modules: &[String]) -> BTreeMap<String, u64> {
+    let mut ids: BTreeMap<String, u64> = modules
+        .iter()
+        .filter_map(|module| Some((module.clone(), *previous.get(module)?)))
+        .collect();
+    let mut added: Vec<&String> = modules.iter().filter(|m| !ids.contains_key(*m)).collect();
+    added.sort();
+
+    // A single example replacing a single one is most likely the same one, renamed.
+    let removed: Vec<u64> = previous
+        .iter()
+        .filter(|(module, _)| !modules.contains(module))
+        .map(|(_, id)| *id)
+        .collect();
+    if let ([module], [id]) = (added.as_slice(), removed.as_slice()) {
+        ids.insert(module.to_string(), *id);
+        return ids;
+    }
+
+    let next = previous
+        .values()
+        .chain(ids.values())
+        .max()
+        .map_or(1, |id| id + 1);
+    for (id, module) in (next..).zip(added) {
+        ids.insert(module.clone(), id);
+    }
+    ids
+}
+
+/// The contents of the id file listing `ids`, ordered by id.
+pub fn render(ids: &BTreeMap<String, u64>) -> String {
+    let mut lines: Vec<(u64, &str)> = ids.iter().map(|(m, id)| (*id, m.as_str())).collect();
+    lines.sort();
+    let mut out = HEADER.to_string();
+    for (id, module) in lines {
+        out.push_str(&format!("{} {}\n", id, module));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_ids_across_renames() {
+        let previous = parse("# ids\n1 hello\n2 calc\n\n3 sibling::flex\n").unwrap();
+        let modules = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+        // Renaming `calc` keeps its id, as it is the only change.
+        let ids = assign(
+            &previous,
+            &modules(&["hello", "calculator", "sibling::flex"]),
+        );
+        assert_eq!(ids["calculator"], 2);
+
+        // Added examples get the next ids, after the ones of removed examples.
+        let ids = assign(&previous, &modules(&["hello", "anim", "zoo"]));
+        assert_eq!(
+            render(&ids).lines().skip(2).collect::<Vec<_>>(),
+            vec!["1 hello", "4 anim", "5 zoo"]
+        );
+
+        assert!(parse("1 hello\n1 calc\n")
+            .unwrap_err()
+            .starts_with("line 2:"));
+    }
+}
@@ -0,0 +1,110 @@
+// Copyright 2020 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Information about the build shown in the footer of the index, to tell which build produced a
+//! deployed site.
+
+use std::env;
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The version, commit and date of the build.
+#[derive(Debug, Clone)]
+pub struct BuildInfo {
+    /// The version of this crate.
+    pub version: String,
+    /// The short hash of the commit built, if known.
+    pub commit: Option<String>,
+    /// The date of the build, as `YYYY-MM-DD`.
+    pub date: String,
+}
+
+impl BuildInfo {
+    /// Collect the information about the build of the crate in `crate_dir`.
+    ///
+    /// The commit is taken from `RCCHAT_GIT_COMMIT`, or asked from git, and left out if neither
+    /// knows it. The date is the one of `SOURCE_DATE_EPOCH` for reproducible builds, or today.
+    pub fn detect(crate_dir: &Path) -> BuildInfo {
+        crate::rerun_if_env_changed("RCCHAT_GIT_COMMIT");
+        crate::rerun_if_env_changed("SOURCE_DATE_EPOCH");
+        let commit = env::var("RCCHAT_GIT_COMMIT")
+            .ok()
+            .filter(|c| !c.is_empty())
+            .or_else(|| git_commit(crate_dir))
+            .map(|c| c.chars().take(12).collect::<String>());
+        if let Some(commit) = &commit {
+            if !commit.chars().all(|c| c.is_ascii_hexdigit()) {
+                panic!("RCCHAT_GIT_COMMIT: `{}` is not a commit hash", commit);
+            }
+        }
+        let timestamp = match env::var("SOURCE_DATE_EPOCH") {
+            Ok(epoch) => epoch.trim().parse().unwrap_or_else(|_| {
+                panic!(
+                    "SOURCE_DATE_EPOCH must be a number of seconds, found `{}`",
+                    epoch
+                )
+            }),
+            Err(_) => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs() as i64),
+        };
+        BuildInfo {
+            version: env::var("CARGO_PKG_VERSION").unwrap(),
+            commit,
+            date: date(timestamp),
+        }
+    }
+}
+
+/// The short hash of the commit checked out in `dir`, if it is in a git repository.
+fn git_commit(dir: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .current_dir(dir)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let commit = String::from_utf8(output.stdout).ok()?;
+    Some(commit.trim().to_string()).filter(|c| !c.is_empty())
+}
+
+/// The UTC date of the unix `timestamp`, as `YYYY-MM-DD`.
+fn date(timestamp: i64) -> String {
+    // Howard Hinnant's `civil_from_days`.
+    let days = timestamp.div_euclid(86400) + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_dates() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(951_782_400), "2000-02-29");
+        assert_eq!(date(1_792_022_399), "2026-10-14");
+        assert_eq!(date(-1), "1969-12-31");
+    }
+}
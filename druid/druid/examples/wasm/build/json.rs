@@ -0,0 +1,361 @@
+// Copyright 2020 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A minimal JSON writer for the data files generated next to the pages, and a parser for the
+//! JSON given in example metadata.
+
+use std::fmt::{self, Display, Formatter, Write};
+
+/// A JSON value. Object members keep their insertion order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(i64),
+    /// A number which isn't an integer, or is out of the range of the integers. Always finite.
+    Float(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// An object with the members `members`.
+    pub fn object<'a>(members: impl IntoIterator<Item = (&'a str, Json)>) -> Json {
+        Json::Object(
+            members
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect(),
+        )
+    }
+
+    /// A string, or null if there is none.
+    pub fn opt_string(s: Option<impl Into<String>>) -> Json {
+        s.map_or(Json::Null, |s| Json::String(s.into()))
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Json {
+        Json::String(s.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Json {
+        Json::String(s)
+    }
+}
+
+impl Display for Json {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Number(n) => write!(f, "{}", n),
+            Json::Float(n) => write!(f, "{}", n),
+            Json::String(s) => write_string(f, s),
+            Json::Array(items) => {
+                f.write_char('[')?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                f.write_char(']')
+            }
+            Json::Object(members) => {
+                f.write_char('{')?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+/// Write `s` as a JSON string. `<`, `>` and `&` are escaped too, so that the output can be embedded
+/// in an html `<script>` as is.
+fn write_string(f: &mut Formatter, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if c < ' ' || "<>&\u{2028}\u{2029}".contains(c) => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+/// Parse a JSON document.
+pub fn parse(src: &str) -> Result<Json, String> {
+    let mut parser = Parser {
+        chars: src.chars().collect(),
+        pos: 0,
+    };
+    parser.skip_whitespace();
+    let value = parser.parse_value()?;
+    parser.skip_whitespace();
+    match parser.peek() {
+        None => Ok(value),
+        Some(c) => Err(format!("unexpected `{}` after the value", c)),
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        match self.bump() {
+            Some(found) if found == c => Ok(()),
+            Some(found) => Err(format!("expected `{}`, found `{}`", c, found)),
+            None => Err(format!("expected `{}`, found the end", c)),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(' ') | Some('\t') | Some('\n') | Some('\r') = self.peek() {
+            self.bump();
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Json, String> {
+        match self.peek() {
+            Some('{') => self.parse_object(),
+            Some('[') => self.parse_array(),
+            Some('"') => self.parse_string().map(Json::String),
+            Some('-') | Some('0'..='9') => self.parse_number(),
+            Some(c) if c.is_ascii_alphabetic() => {
+                let start = self.pos;
+                while matches!(self.peek(), Some(c) if c.is_ascii_alphabetic()) {
+                    self.bump();
+                }
+                let word: String = self.chars[start..self.pos].iter().collect();
+                match word.as_str() {
+                    "null" => Ok(Json::Null),
+                    "true" => Ok(Json::Bool(true)),
+                    "false" => Ok(Json::Bool(false)),
+                    _ => Err(format!("unexpected `{}`", word)),
+                }
+            }
+            Some(c) => Err(format!("unexpected `{}`", c)),
+            None => Err("expected a value, found the end".to_string()),
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.bump();
+            return Ok(Json::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            self.skip_whitespace();
+            members.push((key, self.parse_value()?));
+            self.skip_whitespace();
+            match self.bump() {
+                Some(',') => (),
+                Some('}') => return Ok(Json::Object(members)),
+                Some(c) => return Err(format!("unexpected `{}` in object", c)),
+                None => return Err("unterminated object".to_string()),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.bump();
+            return Ok(Json::Array(items));
+        }
+        loop {
+            self.skip_whitespace();
+            items.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.bump() {
+                Some(',') => (),
+                Some(']') => return Ok(Json::Array(items)),
+                Some(c) => return Err(format!("unexpected `{}` in array", c)),
+                None => return Err("unterminated array".to_string()),
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<Json, String> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_ascii_digit() || "+-.eE".contains(c)) {
+            self.bump();
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        // Rust accepts some numbers JSON doesn't, such as `1.` or `.5`, so check the grammar first.
+        let digits = text.strip_prefix('-').unwrap_or(&text);
+        let (mantissa, exponent) = match digits.find(['e', 'E']) {
+            Some(i) => (&digits[..i], Some(&digits[i + 1..])),
+            None => (digits, None),
+        };
+        let (integer, fraction) = match mantissa.find('.') {
+            Some(i) => (&mantissa[..i], Some(&mantissa[i + 1..])),
+            None => (mantissa, None),
+        };
+        let all_digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+        let valid = all_digits(integer)
+            && (integer == "0" || !integer.starts_with('0'))
+            && fraction.is_none_or(all_digits)
+            && exponent.is_none_or(|e| all_digits(e.strip_prefix(['+', '-']).unwrap_or(e)));
+        if !valid {
+            return Err(format!("invalid number `{}`", text));
+        }
+        if let (None, None, Ok(n)) = (fraction, exponent, text.parse()) {
+            return Ok(Json::Number(n));
+        }
+        match text.parse::<f64>() {
+            Ok(n) if n.is_finite() => Ok(Json::Float(n)),
+            _ => Err(format!("number `{}` is out of range", text)),
+        }
+    }
+
+    fn parse_hex(&mut self) -> Result<u32, String> {
+        let code: String = (0..4).filter_map(|_| self.bump()).collect();
+        u32::from_str_radix(&code, 16)
+            .ok()
+            .filter(|_| code.len() == 4)
+            .ok_or_else(|| format!("invalid unicode escape `\\u{}`", code))
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(s),
+                Some('\\') => s.push(match self.bump() {
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some('/') => '/',
+                    Some('b') => '\u{8}',
+                    Some('f') => '\u{c}',
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some('t') => '\t',
+                    Some('u') => {
+                        let mut code = self.parse_hex()?;
+                        // Characters outside the basic plane are escaped as a surrogate pair.
+                        if (0xd800..0xdc00).contains(&code) {
+                            self.expect('\\')?;
+                            self.expect('u')?;
+                            let low = self.parse_hex()?;
+                            if !(0xdc00..0xe000).contains(&low) {
+                                return Err(format!("invalid surrogate pair `\\u{:x}`", code));
+                            }
+                            code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                        }
+                        std::char::from_u32(code)
+                            .ok_or_else(|| format!("invalid unicode escape `\\u{:x}`", code))?
+                    }
+                    Some(c) => return Err(format!("invalid escape `\\{}`", c)),
+                    None => return Err("unterminated string".to_string()),
+                }),
+                Some(c) if c < ' ' => {
+                    return Err("unescaped control character in string".to_string())
+                }
+                Some(c) => s.push(c),
+                None => return Err("unterminated string".to_string()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_strings_for_scripts() {
+        let json = Json::object(vec![
+            ("name", "</script>\"a\"\n".into()),
+            ("list", Json::Array(vec![Json::Number(1), Json::Null])),
+        ]);
+        assert_eq!(
+            json.to_string(),
+            r#"{"name":"\u003c/script\u003e\"a\"\n","list":[1,null]}"#
+        );
+    }
+
+    #[test]
+    fn parses_documents() {
+        let json =
+            parse(r#" {"id": "canvas", "n": [-1, 2.5e1, true, null], "s": "\u00e9\ud83d\ude00"} "#);
+        assert_eq!(
+            json,
+            Ok(Json::object(vec![
+                ("id", "canvas".into()),
+                (
+                    "n",
+                    Json::Array(vec![
+                        Json::Number(-1),
+                        Json::Float(25.0),
+                        Json::Bool(true),
+                        Json::Null
+                    ])
+                ),
+                ("s", "\u{e9}\u{1f600}".into()),
+            ]))
+        );
+        for invalid in &[
+            "{'id': 1}",
+            "[1,]",
+            "01",
+            "1.",
+            "{\"a\" 1}",
+            "[1] 2",
+            "1e400",
+            "",
+        ] {
+            assert!(parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,67 @@
+// Copyright 2020 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Checking that the relative links of the generated pages lead to files of the site.
+
+use std::path::{Path, PathBuf};
+
+use crate::meta::relative_urls;
+
+/// The relative links and sources of the page at `page`, with the contents `doc`, whose targets
+/// don't exist. Targets inside `pkg_dir` are left out, as the bundle is built after the pages.
+pub fn dangling<'a>(doc: &'a str, page: &Path, pkg_dir: Option<&Path>) -> Vec<&'a str> {
+    let dir = page.parent().unwrap_or_else(|| Path::new(""));
+    relative_urls(doc)
+        .into_iter()
+        .filter(|url| {
+            // Resolved lexically, so that a link through a missing directory is dangling too.
+            let mut target = PathBuf::from(dir);
+            for segment in url.split('/') {
+                match segment {
+                    ".." => {
+                        target.pop();
+                    }
+                    "" | "." => (),
+                    segment => target.push(segment),
+                }
+            }
+            !pkg_dir.is_some_and(|pkg_dir| target.starts_with(pkg_dir)) && !target.exists()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn finds_dangling_links() {
+        let dir = crate::fixture_dir("links");
+        fs::create_dir_all(dir.join("html")).unwrap();
+        fs::write(dir.join("index.html"), "").unwrap();
+        fs::write(dir.join("html/calc.html"), "").unwrap();
+        let doc = concat!(
+            r#"<a href="../index.html#calc">All</a><a href="./calc.html">Calc</a>"#,
+            r#"<a href="hello.html">Hello</a><img src="../screenshots/calc.png">"#,
+            r##"<a href="https://example.org/missing.html">x</a><a href="#top">top</a>"##,
+            r#"<link rel="modulepreload" href="../pkg/druid_wasm_examples.js">"#,
+        );
+        let page = dir.join("html/calc.html");
+        assert_eq!(
+            dangling(doc, &page, Some(&dir.join("pkg"))),
+            ["../screenshots/calc.png", "hello.html"]
+        );
+    }
+}
@@ -0,0 +1,1149 @@
+// Copyright 2020 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod badge;
+mod budget;
+mod capture;
+mod changelog;
+mod compress;
+mod config;
+mod csv;
+mod deps;
+mod digest;
+mod discover;
+mod feed;
+mod highlight;
+mod hook;
+mod html;
+mod ids;
+mod info;
+mod json;
+mod links;
+mod manifest;
+mod meta;
+mod output;
+mod qr;
+mod report;
+mod server;
+mod spec;
+mod sw;
+mod zip;
+
+use std::collections::BTreeSet;
+use std::io::{ErrorKind, Result, Write};
+use std::path::{Component, Path, PathBuf};
+#[cfg(windows)]
+use std::process::Stdio;
+#[cfg(any(windows, test))]
+use std::process::{Child, ExitStatus};
+use std::time::Duration;
+#[cfg(any(windows, test))]
+use std::time::Instant;
+use std::{env, fs, thread};
+
+use config::Config;
+use discover::{check_examples_dir, discover, module_ident, Discovery, Example, Root};
+use html::Markup;
+use output::Output;
+use report::Report;
+
+/// Examples known to not work with WASM are skipped, along with the reason why. Ideally this list
+/// will eventually be empty.
+pub const EXCEPTIONS: &[(&str, &str)] = &[
+    ("svg", "usvg doesn't currently build with WASM."),
+    (
+        "ext_event",
+        "WASM doesn't currently support spawning threads.",
+    ),
+    (
+        "blocking_function",
+        "WASM doesn't currently support spawning threads.",
+    ),
+];
+
+/// The name of the file in `OUT_DIR` collecting the warnings of the last build.
+const WARNINGS_FILE: &str = "warnings.txt";
+
+/// Emit a warning to be shown by cargo, and record it in the warnings file.
+pub fn warn(msg: &str) {
+    println!("cargo:warning={}", msg);
+    // Appending each warning as it's emitted keeps the ones emitted before a panic.
+    if let Some(out_dir) = env::var_os("OUT_DIR") {
+        let path = Path::new(&out_dir).join(WARNINGS_FILE);
+        let line = format!("{}\n", msg.replace('\n', " "));
+        let written = fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(line.as_bytes()));
+        if let Err(e) = written {
+            println!(
+                "cargo:warning=failed to record this warning in {}: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+}
+
+/// Rerun the build script when the environment variable `name` changes.
+pub fn rerun_if_env_changed(name: &str) {
+    println!("cargo:rerun-if-env-changed={}", name);
+}
+
+/// Rerun the build script when the file or directory at `path` changes.
+///
+/// Cargo reruns a build script telling it about a path which doesn't exist on every build, so
+/// those are left out; creating an optional input such as `.keep` is only noticed once another
+/// input changes.
+pub fn rerun_if_changed(path: &Path) {
+    if path.exists() {
+        println!("cargo:rerun-if-changed={}", path.display());
+    }
+}
+
+/// Rerun the build script when the examples in `dir` change.
+///
+/// The directory is tracked as a whole, unless it contains `crate_dir` as it does by default: the
+/// build writes its files into this crate, which would rerun it on every build. Only its other
+/// entries are tracked then, so adding an example next to them is only noticed once another input
+/// changes.
+fn rerun_if_examples_changed(dir: &Path, crate_dir: &Path) -> Result<()> {
+    let dir = fs::canonicalize(dir)?;
+    let crate_dir = fs::canonicalize(crate_dir)?;
+    if !crate_dir.starts_with(&dir) {
+        rerun_if_changed(&dir);
+        return Ok(());
+    }
+    for entry in dir.read_dir()? {
+        let path = entry?.path();
+        if !crate_dir.starts_with(&path) {
+            rerun_if_changed(&path);
+        }
+    }
+    Ok(())
+}
+
+/// Give `path` to the `owner` configured with `RCCHAT_OWNER`, if any, without following symlinks.
+///
+/// Panics if the owner can't be changed, e.g. when not running as root.
+#[cfg_attr(not(unix), allow(unused_variables))]
+pub fn set_owner(path: &Path, owner: Option<(u32, u32)>) {
+    #[cfg(unix)]
+    if let Some((uid, gid)) = owner {
+        std::os::unix::fs::lchown(path, Some(uid), Some(gid)).unwrap_or_else(|e| {
+            panic!(
+                "RCCHAT_OWNER: failed to change the owner of {}: {}",
+                path.display(),
+                e
+            )
+        });
+    }
+}
+
+/// An empty directory for the fixtures of a test, removed again when dropped.
+#[cfg(test)]
+struct FixtureDir(PathBuf);
+
+#[cfg(test)]
+impl std::ops::Deref for FixtureDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl AsRef<Path> for FixtureDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for FixtureDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Create an empty directory for the fixtures of the test `name`.
+#[cfg(test)]
+fn fixture_dir(name: &str) -> FixtureDir {
+    let dir = env::temp_dir().join(format!("druid-wasm-build-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    FixtureDir(dir)
+}
+
+/// Take an exclusive lock on the build of the crate with the sources in `src_dir`, waiting for
+/// other builds holding it.
+fn lock(src_dir: &Path, owner: Option<(u32, u32)>) -> Result<fs::File> {
+    let path = src_dir.join(".build.lock");
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(&path)?;
+    // Builds as another user have to be able to open the lock file too.
+    set_owner(&path, owner);
+    file.lock()?;
+    Ok(file)
+}
+
+/// The path of the generated file `path` relative to `site_dir`, with `/` separators, or `None`
+/// if it isn't part of the site, being outside of it or in `src`.
+fn site_relative(path: &Path, site_dir: &Path) -> Option<String> {
+    let relative = path.strip_prefix(site_dir).ok()?;
+    if relative.starts_with("src") {
+        return None;
+    }
+    let segments: Vec<String> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect();
+    Some(segments.join("/"))
+}
+
+/// The files of the local bundle in `site_dir` for `RCCHAT_SITE_ZIP_PKG`, by their path relative
+/// to it. A bundle which is hosted elsewhere, outside of `site_dir` or not built yet is left out
+/// with a warning.
+fn pkg_files(config: &Config, site_dir: &Path) -> Result<Vec<(String, Vec<u8>)>> {
+    let pkg_dir = match config.local_pkg_dir(site_dir) {
+        Some(pkg_dir) if pkg_dir.starts_with(site_dir) => pkg_dir,
+        _ => {
+            warn(&format!(
+                "RCCHAT_SITE_ZIP_PKG: the bundle at {} isn't inside {}, so it is left out of site.zip",
+                config.pkg_base,
+                site_dir.display()
+            ));
+            return Ok(Vec::new());
+        }
+    };
+    if !pkg_dir.is_dir() {
+        warn(&format!(
+            "RCCHAT_SITE_ZIP_PKG: there is no bundle in {} yet, so it is left out of site.zip",
+            pkg_dir.display()
+        ));
+        return Ok(Vec::new());
+    }
+    let mut files = Vec::new();
+    let mut dirs = vec![pkg_dir];
+    while let Some(dir) = dirs.pop() {
+        for entry in dir.read_dir()? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if let Some(name) = site_relative(&path, site_dir) {
+                files.push((name, fs::read(&path)?));
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Apply `f` to all `items` on up to `jobs` threads, returning the results in order.
+fn parallel_map<T: Sync, R: Send>(items: &[T], jobs: usize, f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    if jobs <= 1 || items.len() <= 1 {
+        return items.iter().map(f).collect();
+    }
+    let chunk = items.len().div_ceil(jobs);
+    let f = &f;
+    thread::scope(|scope| {
+        let workers: Vec<_> = items
+            .chunks(chunk)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(f).collect::<Vec<R>>()))
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect()
+    })
+}
+
+/// Check that `dst` is already a directory, for `RCCHAT_SKIP_LINK`.
+fn check_in_place(dst: &Path) -> std::result::Result<(), String> {
+    if dst.is_dir() {
+        return Ok(());
+    }
+    let problem = if dst.exists() {
+        "is not a directory"
+    } else {
+        "does not exist"
+    };
+    Err(format!(
+        "RCCHAT_SKIP_LINK is set, but {} {}. Copy or link the examples there before the build, or \
+         unset RCCHAT_SKIP_LINK to have the build link them.",
+        dst.display(),
+        problem
+    ))
+}
+
+/// Check that linking `src` to `dst` stays inside `crate_dir`: `dst` must be a path inside it, and
+/// `src` must be an existing directory which is not inside the `src` directory of the crate, where
+/// the links are created.
+fn check_link(src: &Path, dst: &Path, crate_dir: &Path) -> std::result::Result<(), String> {
+    let inside = dst
+        .strip_prefix(crate_dir)
+        .map(|rel| rel.components().all(|c| matches!(c, Component::Normal(_))))
+        .unwrap_or(false);
+    let crate_dir = crate_dir
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve {}: {}", crate_dir.display(), e))?;
+    // The parent may be reached through a link itself.
+    let parent = dst.parent().and_then(|parent| parent.canonicalize().ok());
+    if !inside || !matches!(parent, Some(parent) if parent.starts_with(&crate_dir)) {
+        return Err(format!(
+            "Refusing to create a link at {}, which is outside of {}",
+            dst.display(),
+            crate_dir.display()
+        ));
+    }
+
+    let src_dir = src
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve {}: {}", src.display(), e))?;
+    if !src_dir.is_dir() {
+        return Err(format!("{} is not a directory", src.display()));
+    }
+    if src_dir.starts_with(crate_dir.join("src")) {
+        return Err(format!(
+            "Refusing to link {}, which is inside the src directory of {}",
+            src.display(),
+            crate_dir.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Create a platform specific link from `src` to the `dst` directory. `timeout` bounds the wait
+/// for the subprocess creating it on Windows.
+#[inline]
+#[cfg_attr(not(windows), allow(unused_variables))]
+fn link_dir(src: &Path, dst: &Path, timeout: Duration) {
+    #[cfg(unix)]
+    link_dir_unix(src, dst);
+    #[cfg(windows)]
+    link_dir_windows(src, dst, timeout);
+}
+
+#[cfg(unix)]
+fn link_dir_unix(src: &Path, dst: &Path) {
+    let err = std::os::unix::fs::symlink(src, dst).err();
+    match err {
+        None => (),
+        Some(err) if err.kind() == ErrorKind::AlreadyExists => {
+            // Copying the crate while following links turns them into directories, which may hold
+            // edits, so they aren't removed.
+            let link = fs::read_link(dst).unwrap_or_else(|_| {
+                panic!(
+                    "{} is not a link to {}, it may have been copied along with the crate; \
+                     remove it to have it linked again",
+                    dst.display(),
+                    src.display()
+                )
+            });
+            // Replace a link left by a previous build which pointed elsewhere, such as an absolute
+            // link into the previous location of a crate which was moved since.
+            if link != src {
+                if !dst.exists() {
+                    warn(&format!(
+                        "{} pointed at {}, which doesn't exist anymore, e.g. as the crate was \
+                         moved; it now points at {}",
+                        dst.display(),
+                        link.display(),
+                        src.display()
+                    ));
+                }
+                fs::remove_file(dst)
+                    .and_then(|()| std::os::unix::fs::symlink(src, dst))
+                    .unwrap_or_else(|e| panic!("Failed to replace symlink: {}", e));
+            }
+        }
+        Some(err) => panic!("Failed to create symlink: {}", err),
+    }
+}
+
+#[cfg(windows)]
+fn link_dir_windows(src: &Path, dst: &Path, timeout: Duration) {
+    // First we have to delete any previous link,
+    // especially because a junction is an absolute path reference
+    // that becomes invalid if one of our ancestor directories gets renamed/moved.
+    let err = fs::remove_dir(dst).err(); // Safe as it errors when directory isn't empty
+    match err {
+        None => (),
+        Some(err) if err.kind() == ErrorKind::NotFound => (),
+        Some(err) => panic!("Failed to remove directory: {}", err),
+    }
+    // Attempt to create a symlink, which will work with either
+    // * Admininstrator privileges
+    // * New enough Windows with developer mode enabled
+    if std::os::windows::fs::symlink_dir(src, dst).is_ok() {
+        return;
+    }
+    // Otherwise fall back to creating a junction instead,
+    // by using Command Prompt's inbuilt 'mklink' command.
+    let mut child = std::process::Command::new("cmd")
+        .arg("/C") // Run a command and exit
+        .arg("mklink")
+        .arg("/J") // Junction
+        .arg(dst.as_os_str())
+        .arg(src.as_os_str())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap_or_else(|e| panic!("Failed to run mklink: {}", e));
+    // Antivirus software has been seen holding up `cmd` indefinitely.
+    let status = wait_timeout(&mut child, timeout)
+        .unwrap_or_else(|e| panic!("Failed to wait for mklink: {}", e))
+        .unwrap_or_else(|| {
+            panic!(
+                "mklink didn't link {} within {} seconds, and was killed; this can be caused by \
+                 antivirus software scanning the build. Raise RCCHAT_LINK_TIMEOUT, or create the \
+                 junction by hand with `mklink /J {} {}`",
+                dst.display(),
+                timeout.as_secs(),
+                dst.display(),
+                src.display()
+            )
+        });
+    if !status.success() {
+        let mut stderr = String::new();
+        if let Some(mut pipe) = child.stderr.take() {
+            let _ = std::io::Read::read_to_string(&mut pipe, &mut stderr);
+        }
+        panic!(
+            "mklink failed to link {} to {} with {}: {}",
+            dst.display(),
+            src.display(),
+            status,
+            stderr.trim()
+        );
+    }
+    // Make sure the directory exists now
+    if !dst.exists() {
+        panic!("Failed to create a link");
+    }
+}
+
+/// Wait up to `timeout` for `child` to exit, returning its status, or kill it and return `None`.
+#[cfg(any(windows, test))]
+fn wait_timeout(child: &mut Child, timeout: Duration) -> Result<Option<ExitStatus>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            // It may have exited in the meantime, which `wait` reaps either way.
+            let _ = child.kill();
+            child.wait()?;
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+fn main() -> Result<()> {
+    let mut report = Report::start();
+    // Start the warnings of this build afresh, before reading the configuration warns.
+    let out_dir = PathBuf::from(&env::var("OUT_DIR").unwrap());
+    fs::write(out_dir.join(WARNINGS_FILE), "")?;
+    let crate_dir = PathBuf::from(&env::var("CARGO_MANIFEST_DIR").unwrap());
+    let src_dir = crate_dir.join("src");
+    let examples_dir = src_dir.join("examples");
+
+    let config = Config::from_env(&crate_dir);
+    let site_dir = config.site_dir(&crate_dir);
+
+    // Concurrent builds of this crate, e.g. of several profiles, share the links and generated
+    // files, so they take turns. The lock is released when the file is closed, even on panic.
+    let lock = lock(&src_dir, config.owner)?;
+    let mut output = Output::new(&out_dir, &config);
+    report.phase("lock");
+
+    // Create a platform specific link to the examples directory, and to each additional
+    // examples directory, unless the pipeline put them in place itself.
+    if config.skip_link {
+        check_in_place(&examples_dir).unwrap_or_else(|e| panic!("{}", e));
+    } else {
+        check_examples_dir(&config.examples_src).unwrap_or_else(|e| panic!("{}", e));
+        check_link(&config.examples_src, &examples_dir, &crate_dir)
+            .unwrap_or_else(|e| panic!("{}", e));
+        link_dir(&config.examples_src, &examples_dir, config.link_timeout);
+        set_owner(&examples_dir, config.owner);
+    }
+    let mut roots = vec![Root {
+        name: None,
+        dir: examples_dir,
+    }];
+    if !config.extra_roots.is_empty() {
+        let external_dir = src_dir.join("external");
+        if !config.skip_link {
+            fs::create_dir_all(&external_dir)?;
+            set_owner(&external_dir, config.owner);
+        }
+        for extra in &config.extra_roots {
+            let dir = external_dir.join(&extra.name);
+            if config.skip_link {
+                check_in_place(&dir).unwrap_or_else(|e| panic!("RCCHAT_EXTRA_EXAMPLES: {}", e));
+            } else {
+                check_link(&extra.path, &dir, &crate_dir)
+                    .unwrap_or_else(|e| panic!("RCCHAT_EXTRA_EXAMPLES: {}", e));
+                link_dir(&extra.path, &dir, config.link_timeout);
+                set_owner(&dir, config.owner);
+            }
+            roots.push(Root {
+                name: Some(extra.name.clone()),
+                dir,
+            });
+        }
+    }
+
+    // Once told about any input, cargo no longer reruns the build whenever this crate changes,
+    // so every input is named: the environment variables as they're read, and the files here.
+    for root in &roots {
+        rerun_if_examples_changed(&root.dir, &crate_dir)?;
+    }
+    report.phase("linking");
+
+    let mut discovery = discover(&roots, &config.separator, config.strict)?;
+    for example in &mut discovery.examples {
+        example.meta.resolve_assets(&example.path, &config);
+        if config.doc_descriptions {
+            example.meta.describe_from_docs(&example.source);
+        }
+        // Captured screenshots in the assets directory aren't found by default.
+        if config.capture && example.meta.screenshot.is_none() {
+            let captured = capture::target(example, &config);
+            example.meta.screenshot = Some(captured).filter(|path| path.is_file());
+        }
+    }
+    if config.feature_gate {
+        discovery.skip_missing_features(|feature| {
+            let name = feature.to_uppercase().replace('-', "_");
+            env::var_os(format!("CARGO_FEATURE_{}", name)).is_some()
+        });
+    }
+    let shortened = discovery
+        .shorten_pages(config.max_page_name, config.strict)
+        .unwrap_or_else(|e| panic!("{}", e));
+    for message in shortened {
+        warn(&message);
+    }
+    if config.flat_pages {
+        discovery
+            .check_flat_pages()
+            .unwrap_or_else(|e| panic!("{}", e));
+    }
+    // Persistent ids keep the order stable as examples are renamed, for committed output.
+    if config.sort == discover::SortMode::Id {
+        let id_file = crate_dir.join(ids::FILE);
+        rerun_if_changed(&id_file);
+        let previous = match fs::read_to_string(&id_file) {
+            Ok(src) => ids::parse(&src).unwrap_or_else(|e| panic!("{}: {}", id_file.display(), e)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Default::default(),
+            Err(err) => panic!("Failed to read {}: {}", id_file.display(), err),
+        };
+        // Examples left out by the feature gate keep their ids for when it lets them in again.
+        let modules: Vec<String> = discovery
+            .examples
+            .iter()
+            .map(Example::module_path)
+            .chain(discovery.missing_features.keys().cloned())
+            .collect();
+        let assigned = ids::assign(&previous, &modules);
+        for example in &mut discovery.examples {
+            example.id = assigned[&example.module_path()];
+        }
+        discovery.examples.sort_by_key(|example| example.id);
+        if assigned != previous {
+            output::replace(&id_file, ids::render(&assigned))?;
+        }
+    }
+    let Discovery {
+        examples, skipped, ..
+    } = &discovery;
+
+    // Finding no examples at all almost always means that the examples directories are
+    // misconfigured, while the feature gate leaves examples out deliberately.
+    if examples.is_empty() {
+        if discovery.missing_features.is_empty() {
+            let dirs: Vec<String> = std::iter::once(&config.examples_src)
+                .chain(config.extra_roots.iter().map(|extra| &extra.path))
+                .map(|dir| dir.display().to_string())
+                .collect();
+            let message = format!(
+                "no examples were found in {}, so examples.in is empty; \
+                 check RCCHAT_EXAMPLES_SRC and RCCHAT_EXTRA_EXAMPLES",
+                dirs.join(", ")
+            );
+            if config.strict {
+                panic!("{}", message);
+            }
+            warn(&message);
+        } else {
+            warn("all examples require features which aren't enabled, so examples.in is empty");
+        }
+    }
+    report.phase("discovery");
+
+    // Let the crate know which examples were skipped, e.g. for tests to exclude them with
+    // `#[cfg(not(example_skipped = "svg"))]`. Any example name is an expected value.
+    let names: Vec<String> = examples
+        .iter()
+        .map(|e| &e.stem)
+        .chain(skipped)
+        .map(|stem| format!("{:?}", stem))
+        .collect();
+    println!(
+        "cargo:rustc-check-cfg=cfg(example_skipped, values({}))",
+        names.join(", ")
+    );
+    for stem in skipped {
+        println!("cargo:rustc-cfg=example_skipped={:?}", stem);
+    }
+
+    // Generate example module and the necessary html documents.
+
+    // Declare the newly found example modules in examples.in. Vendored setups commit the file, so
+    // they get a header which doesn't tell otherwise.
+    let header = if config.vendored {
+        "// This file is automatically generated by build/main.rs, change the build script instead."
+    } else {
+        "// This file is automatically generated and must not be committed."
+    };
+    let mut examples_in = format!("\n{}\n", header);
+    // Crates also building natively can compile the examples for WASM targets only, gating the
+    // `examples` module, or each module declared at the top level in the flat form.
+    let wasm_only = if config.wasm_only {
+        "#[cfg(target_arch = \"wasm32\")]\n"
+    } else {
+        ""
+    };
+    // The flat form leaves out the `examples` module, so the modules are declared in the file
+    // including it.
+    let (indent, top_cfg) = if config.flat_modules {
+        ("", wasm_only)
+    } else {
+        examples_in.push_str(
+            "\n/// This is a module collecting all valid examples in the parent examples directory.\n",
+        );
+        examples_in.push_str(wasm_only);
+        examples_in.push_str("mod examples {\n");
+        (config.indent.as_str(), "")
+    };
+    let nested = format!("{}{}", indent, config.indent);
+
+    // With feature gating, the modules of examples requiring features are only compiled with them.
+    let cfg = |example: &Example, indent: &str| {
+        if !config.feature_gate || example.meta.features.is_empty() {
+            return String::new();
+        }
+        let features: Vec<String> = example
+            .meta
+            .features
+            .iter()
+            .map(|feature| format!("feature = {:?}", feature))
+            .collect();
+        if features.len() == 1 {
+            format!("{}#[cfg({})]\n", indent, features[0])
+        } else {
+            format!("{}#[cfg(all({}))]\n", indent, features.join(", "))
+        }
+    };
+    // Descriptions can be carried over to the documentation of the modules.
+    let doc = |example: &Example, indent: &str| match &example.meta.description {
+        Some(description) if config.module_docs => {
+            format!("{}#[doc = {:?}]\n", indent, description)
+        }
+        _ => String::new(),
+    };
+    let vis = config.module_visibility;
+    for example in examples.iter().filter(|e| e.root.is_none()) {
+        examples_in.push_str(&doc(example, indent));
+        examples_in.push_str(&cfg(example, indent));
+        examples_in.push_str(top_cfg);
+        if config.flat_modules {
+            examples_in.push_str(&format!("#[path = \"examples/{}.rs\"]\n", example.stem));
+        }
+        examples_in.push_str(&format!(
+            "{}{}mod {};\n",
+            indent,
+            vis,
+            module_ident(&example.stem)
+        ));
+    }
+    // Examples of additional roots are nested in a module per root, pointing at the linked files.
+    for root in roots.iter().skip(1) {
+        let name = root.name.as_ref().unwrap();
+        examples_in.push_str(top_cfg);
+        examples_in.push_str(&format!("{}{}mod {} {{\n", indent, vis, module_ident(name)));
+        for example in examples.iter().filter(|e| e.root.as_ref() == Some(name)) {
+            let path = root.dir.join(&example.stem).with_extension("rs");
+            examples_in.push_str(&doc(example, &nested));
+            examples_in.push_str(&cfg(example, &nested));
+            examples_in.push_str(&format!(
+                "{indent}#[path = {:?}]\n{indent}{vis}mod {};\n",
+                path.display().to_string(),
+                module_ident(&example.stem),
+                indent = nested,
+                vis = vis
+            ));
+        }
+        examples_in.push_str(&format!("{}}}\n", indent));
+    }
+
+    if !config.flat_modules {
+        examples_in.push('}');
+    }
+
+    let markup = config.markup;
+    if let Some(only) = &config.only_regen {
+        if !examples.iter().any(|e| &e.page == only) {
+            panic!("RCCHAT_ONLY_REGEN: there is no example `{}`", only);
+        }
+    }
+    // A local bundle left by a previous build has to be built for the way the pages load it.
+    if let Some(pkg_dir) = config.local_pkg_dir(&site_dir) {
+        let bundle = pkg_dir.join("druid_wasm_examples.js");
+        if let Ok(js) = fs::read_to_string(&bundle) {
+            if let Err(e) = html::check_bundle(&js, config.script_mode) {
+                let message = format!("{}: {}", bundle.display(), e);
+                if config.strict {
+                    panic!("{}", message);
+                }
+                warn(&message);
+            }
+        }
+    }
+
+    let mut pages = Vec::new();
+    for example in examples {
+        let file = format!("{}.{}", example.page, markup.extension());
+        let html_path = config.pages_dir(&site_dir).join(&file);
+
+        // Leave the pages of other examples alone when only regenerating a single one.
+        if matches!(&config.only_regen, Some(only) if only != &example.page) {
+            output.retain_page(&html_path);
+            if config.example_json {
+                output.retain(&html_path.with_extension("json"));
+            }
+        } else {
+            pages.push((example, file, html_path));
+        }
+    }
+
+    // Create an html document for each example.
+    let rendered = parallel_map(&pages, config.jobs, |(example, file, _)| {
+        let html = html::example_page(example, examples, &config);
+        if markup == Markup::Xhtml {
+            html::check_well_formed(&html)
+                .unwrap_or_else(|e| panic!("{} is not well-formed: {}", file, e));
+        }
+        html
+    });
+    report.phase("rendering");
+
+    // Write out the html files into a designated html directory located in crate root.
+    for ((_, file, html_path), html) in pages.iter().zip(rendered) {
+        output
+            .write_page(html_path, html)
+            .unwrap_or_else(|e| panic!("Failed to create {}: {}", file, e));
+    }
+
+    // Write out the metadata of each example next to its page.
+    if config.example_json {
+        for (example, _, html_path) in &pages {
+            output.write(
+                &html_path.with_extension("json"),
+                hook::example_json(example, None, &config).to_string(),
+            )?;
+        }
+    }
+
+    // Make sure each page written runs the entry function of its example.
+    for (example, file, html_path) in &pages {
+        if output.is_kept(html_path) {
+            continue;
+        }
+        let html = fs::read_to_string(html_path)?;
+        html::check_entry(&html, &example.entry, config.script_mode)
+            .unwrap_or_else(|e| panic!("{}: {}", file, e));
+    }
+
+    // Write out the contents of the examples.in module.
+    output.write(&src_dir.join("examples.in"), examples_in)?;
+
+    // The pages whose links are checked once all files are written.
+    let mut linking: Vec<PathBuf> = pages.iter().map(|(_, _, path)| path.clone()).collect();
+
+    // Write out the index.html file, and the further pages of a paginated index.
+    let mut index_files = Vec::new();
+    for (index_file, index) in html::index_pages(examples, skipped, &config) {
+        if markup == Markup::Xhtml {
+            html::check_well_formed(&index)
+                .unwrap_or_else(|e| panic!("{} is not well-formed: {}", index_file, e));
+        }
+        output.write_page(&site_dir.join(&index_file), index)?;
+        linking.push(site_dir.join(&index_file));
+        index_files.push(index_file);
+    }
+
+    // Write out the landing page linking the pages of each profile.
+    if config.profile_dir.is_some() {
+        let landing_file = format!("index.{}", markup.extension());
+        let landing = html::profiles_page(&config);
+        if markup == Markup::Xhtml {
+            html::check_well_formed(&landing)
+                .unwrap_or_else(|e| panic!("{} is not well-formed: {}", landing_file, e));
+        }
+        output.write_page(&crate_dir.join(landing_file), landing)?;
+    }
+
+    // Copy the screenshots shown by the thumbnails and the gallery.
+    let gallery = config.gallery
+        && examples
+            .iter()
+            .any(|e| !e.meta.unlisted && e.meta.screenshot.is_some());
+    if gallery || config.thumbnails || config.gallery_feed {
+        for example in examples {
+            if let (Some(src), Some(file)) = (&example.meta.screenshot, example.screenshot_file()) {
+                rerun_if_changed(src);
+                output.write(&site_dir.join(file), fs::read(src)?)?;
+            }
+        }
+    }
+
+    // Write out the static gallery of screenshots.
+    if gallery {
+        let gallery_file = format!("gallery_static.{}", markup.extension());
+        let gallery = html::gallery_page(examples, &config);
+        if markup == Markup::Xhtml {
+            html::check_well_formed(&gallery)
+                .unwrap_or_else(|e| panic!("{} is not well-formed: {}", gallery_file, e));
+        }
+        output.write_page(&site_dir.join(&gallery_file), gallery)?;
+        linking.push(site_dir.join(gallery_file));
+    }
+
+    // Write out the stylesheet shared by the pages which need it.
+    if config.embed_source {
+        output.write(&site_dir.join("style.css"), html::STYLESHEET)?;
+    }
+
+    // Write out the inventory of the examples for spreadsheets.
+    if config.csv {
+        output.write(
+            &site_dir.join("examples.csv"),
+            csv::examples_csv(&discovery, &config),
+        )?;
+    }
+
+    // Write out the spec of the runtime test of the pages.
+    if config.gallery_feed {
+        output.write(
+            &site_dir.join("gallery.json"),
+            feed::gallery_feed(examples, &config).to_string(),
+        )?;
+    }
+    if config.test_spec {
+        output.write(
+            &site_dir.join("test_spec.json"),
+            spec::test_spec(examples, &config).to_string(),
+        )?;
+    }
+
+    // Write out the badge of the health of the examples.
+    if config.badge {
+        output.write(
+            &site_dir.join("badge.json"),
+            badge::badge_json(examples.len(), skipped.len()).to_string(),
+        )?;
+    }
+
+    // Write out the APIs of druid each example imports.
+    if config.dependencies {
+        output.write(
+            &site_dir.join("dependencies.json"),
+            deps::dependencies_json(examples).to_string(),
+        )?;
+    }
+
+    // Write out the list of screenshots to capture.
+    let captures = if config.capture {
+        capture::needed(examples, &config)
+    } else {
+        Vec::new()
+    };
+    if config.capture {
+        output.write(
+            &site_dir.join("captures.json"),
+            capture::captures_json(&captures, &crate_dir, &site_dir, &config).to_string(),
+        )?;
+    }
+
+    // Write out the policy for crawlers.
+    if let Some(robots) = &config.robots {
+        output.write(&crate_dir.join("robots.txt"), robots)?;
+    }
+
+    // Keep GitHub Pages from running Jekyll, which leaves out files starting with `_`.
+    if config.ghpages {
+        output.write(&crate_dir.join(".nojekyll"), "")?;
+    }
+
+    // Write out the web app manifest, with the icons it refers to.
+    if config.manifest {
+        if config.icons.is_empty() {
+            warn("RCCHAT_MANIFEST: without RCCHAT_ICONS, the examples can't be installed");
+        }
+        for icon in &config.icons {
+            rerun_if_changed(&icon.src);
+            output.write(&site_dir.join(&icon.file), fs::read(&icon.src)?)?;
+        }
+        output.write(
+            &site_dir.join("manifest.webmanifest"),
+            manifest::manifest(&config),
+        )?;
+    }
+
+    // Write out the service worker last, so that its cache is named after all other files.
+    if config.service_worker {
+        let worker = sw::service_worker(
+            &index_files,
+            examples,
+            &config,
+            &env::var("CARGO_PKG_VERSION").unwrap(),
+            output.fingerprint(),
+        );
+        output.write(&site_dir.join("sw.js"), worker)?;
+    }
+
+    // Record which examples were added or removed since the previous build.
+    let current: BTreeSet<String> = examples.iter().map(Example::module_path).collect();
+    if config.changelog {
+        if let Some(previous) = output.previous_examples() {
+            let path = crate_dir.join("examples_changelog.md");
+            changelog::update(
+                &path,
+                &env::var("CARGO_PKG_VERSION").unwrap(),
+                previous,
+                &current,
+            )?;
+            if path.exists() {
+                set_owner(&path, config.owner);
+            }
+        }
+    }
+    output.record_examples(current);
+    report.phase("writing");
+
+    // Measure the pages and the bundle of the previous build, which all pages load, and check them
+    // against the size budgets.
+    let sizes = config.local_pkg_dir(&site_dir).and_then(|pkg_dir| {
+        budget::measure(
+            examples,
+            &pkg_dir,
+            &config.pages_dir(&site_dir),
+            markup.extension(),
+        )
+    });
+    let mut exceeded = Vec::new();
+    if budget::any_budget(examples, &config) {
+        match &sizes {
+            Some(sizes) => exceeded = budget::check(sizes, &config),
+            None => warn("the size budgets aren't checked, as there is no local bundle to measure"),
+        }
+    }
+
+    // Check that the links between the pages lead somewhere, now that all files are written.
+    if config.check_links {
+        let pkg_dir = config.local_pkg_dir(&site_dir);
+        let mut dangling = Vec::new();
+        for page in &linking {
+            let doc = fs::read_to_string(page)?;
+            for url in links::dangling(&doc, page, pkg_dir.as_deref()) {
+                dangling.push(format!(
+                    "{} links to {}, which doesn't exist",
+                    page.display(),
+                    url
+                ));
+            }
+        }
+        if config.strict && !dangling.is_empty() {
+            panic!("Dangling links:\n{}", dangling.join("\n"));
+        }
+        for message in dangling {
+            warn(&message);
+        }
+    }
+
+    // Write out the report last, so that it covers all other files.
+    if config.build_report {
+        let counts = vec![
+            ("examples", examples.len()),
+            ("skipped", skipped.len()),
+            ("rendered", pages.len()),
+            ("files", output.file_count()),
+            ("jobs", config.jobs),
+        ];
+        output.write(
+            &site_dir.join("build_report.json"),
+            report
+                .json(counts, sizes.as_ref().map(budget::json))
+                .to_string(),
+        )?;
+    }
+
+    // Write out the checksums of all generated files of the site last, so that a deploy step can
+    // verify them with `sha256sum -c`.
+    if config.checksums {
+        let sums_path = site_dir.join("SHA256SUMS");
+        let mut sums = String::new();
+        for path in output.files() {
+            let name = match site_relative(path, &site_dir) {
+                Some(name) if path != sums_path => name,
+                _ => continue,
+            };
+            let hash = digest::hex(&digest::sha256(&fs::read(path)?));
+            sums.push_str(&format!("{}  {}\n", hash, name));
+        }
+        output.write(&sums_path, sums)?;
+    }
+
+    // Package the generated site, along with the checksums.
+    if config.site_zip {
+        let zip_path = site_dir.join("site.zip");
+        let mut files = Vec::new();
+        for path in output.files() {
+            let name = match site_relative(path, &site_dir) {
+                Some(name) if path != zip_path && !name.ends_with(".gz") => name,
+                _ => continue,
+            };
+            files.push((name, fs::read(path)?));
+        }
+        if config.site_zip_pkg {
+            files.extend(pkg_files(&config, &site_dir)?);
+        }
+        files.sort();
+        output.write(&zip_path, zip::zip(&files))?;
+    }
+
+    // Write out the configuration snippet for serving the pages last, so that it lists all other
+    // files.
+    if let Some(server) = config.server {
+        let pkg_dir = config.local_pkg_dir(&site_dir);
+        let root = server::root(&site_dir, pkg_dir.as_deref());
+        let site_files = output.files().filter(|path| !path.starts_with(&src_dir));
+        let served = server::served(&root, site_files, pkg_dir.as_deref());
+        output.write(
+            &crate_dir.join(server.file_name()),
+            server.snippet(&root, &served, &config),
+        )?;
+    }
+
+    output.finish()?;
+    drop(lock);
+
+    // Fail once the generated files are recorded, so that the next build doesn't take them for
+    // edited ones.
+    if !exceeded.is_empty() {
+        panic!("Size budgets exceeded:\n{}", exceeded.join("\n"));
+    }
+
+    // Let the user's hook generate its own files off the examples.
+    if let Some(hook) = &config.hook {
+        rerun_if_changed(hook);
+        let data = hook::discovery_json(&discovery, &crate_dir, &config);
+        hook::run(hook, &data, &crate_dir, &out_dir);
+    }
+
+    // Let the capture tool take the missing screenshots, which the next build picks up.
+    if let (Some(tool), false) = (&config.capture_tool, captures.is_empty()) {
+        capture::run(tool, &site_dir.join("captures.json"), &crate_dir);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_links_escaping_the_crate() {
+        let dir = fixture_dir("link_escape");
+        let crate_dir = dir.join("wasm");
+        let examples = dir.join("examples");
+        fs::create_dir_all(crate_dir.join("src/external")).unwrap();
+        fs::create_dir_all(&examples).unwrap();
+
+        let dst = crate_dir.join("src/external/widgets");
+        assert_eq!(check_link(&examples, &dst, &crate_dir), Ok(()));
+
+        let dst = crate_dir.join("src/external/../../../escaped");
+        let err = check_link(&examples, &dst, &crate_dir).unwrap_err();
+        assert!(err.starts_with("Refusing to create a link"), "{}", err);
+
+        let src = crate_dir.join("src/external");
+        let dst = crate_dir.join("src/external/nested");
+        let err = check_link(&src, &dst, &crate_dir).unwrap_err();
+        assert!(err.starts_with("Refusing to link"), "{}", err);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn relinks_moved_crates() {
+        let dir = fixture_dir("moved_crate");
+        let before = dir.join("before");
+        fs::create_dir_all(before.join("examples")).unwrap();
+        fs::create_dir_all(before.join("wasm/src")).unwrap();
+        fs::write(before.join("examples/hello.rs"), "").unwrap();
+        let timeout = Duration::from_secs(1);
+        link_dir(
+            &before.join("examples"),
+            &before.join("wasm/src/examples"),
+            timeout,
+        );
+
+        // The absolute link dangles once the whole tree is moved.
+        let after = dir.join("after");
+        fs::rename(&before, &after).unwrap();
+        let dst = after.join("wasm/src/examples");
+        assert!(!dst.exists());
+
+        link_dir(&after.join("examples"), &dst, timeout);
+        assert_eq!(fs::read_link(&dst).unwrap(), after.join("examples"));
+        assert!(dst.join("hello.rs").is_file());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn kills_hanging_subprocesses() {
+        let mut child = std::process::Command::new("sleep")
+            .arg("10")
+            .spawn()
+            .unwrap();
+        let start = Instant::now();
+        let status = wait_timeout(&mut child, Duration::from_millis(100)).unwrap();
+        assert!(status.is_none());
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let status = wait_timeout(&mut child, Duration::from_secs(5)).unwrap();
+        assert!(status.unwrap().success());
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright 2020 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The web app manifest, which together with the icons makes the examples installable as a
+//! progressive web app.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::json::Json;
+
+/// An icon of the app, copied to `icons/`.
+#[derive(Debug, Clone)]
+pub struct Icon {
+    /// The icon in the source tree.
+    pub src: PathBuf,
+    /// The path of the copy, relative to the directory of the index.
+    pub file: String,
+    /// The media type of the icon.
+    pub mime: &'static str,
+    /// The size of the icon, e.g. `192x192`, or `any` for svg icons.
+    pub sizes: String,
+}
+
+impl Icon {
+    /// Load the png or svg icon at `path`, reading the size of png icons from their header.
+    pub fn load(path: &Path) -> Result<Icon, String> {
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| format!("{} is not a valid file name", path.display()))?;
+        if !path.is_file() {
+            return Err(format!("{} does not exist", path.display()));
+        }
+        let (mime, sizes) = match crate::meta::image_type(path).as_deref() {
+            Some("png") => {
+                let header = fs::read(path)
+                    .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+                let (width, height) = png_size(&header)
+                    .ok_or_else(|| format!("{} is not a png image", path.display()))?;
+                ("image/png", format!("{}x{}", width, height))
+            }
+            Some("svg") => ("image/svg+xml", "any".to_string()),
+            _ => return Err(format!("{} must be a png or svg image", path.display())),
+        };
+        Ok(Icon {
+            src: path.to_path_buf(),
+            file: format!("icons/{}", name),
+            mime,
+            sizes,
+        })
+    }
+}
+
+/// The width and height of the png image `data`, from its header.
+fn png_size(data: &[u8]) -> Option<(u32, u32)> {
+    const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+    if data.len() < 24 || !data.starts_with(SIGNATURE) || &data[12..16] != b"IHDR" {
+        return None;
+    }
+    let read = |i: usize| u32::from_be_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+    Some((read(16), read(20)))
+}
+
+/// The png icon Apple devices show on the home screen, the one closest to their 180x180 pixels.
+pub fn touch_icon(icons: &[Icon]) -> Option<&Icon> {
+    icons
+        .iter()
+        .filter_map(|icon| {
+            let (width, _) = icon.sizes.split_once('x')?;
+            Some((width.parse::<i64>().ok()?, icon))
+        })
+        .min_by_key(|(width, _)| (width - 180).abs())
+        .map(|(_, icon)| icon)
+}
+
+/// Render `manifest.webmanifest`, whose urls are relative to the directory of the index.
+pub fn manifest(config: &Config) -> String {
+    let icons = config
+        .icons
+        .iter()
+        .map(|icon| {
+            Json::object(vec![
+                ("src", icon.file.as_str().into()),
+                ("sizes", icon.sizes.as_str().into()),
+                ("type", icon.mime.into()),
+            ])
+        })
+        .collect();
+    let mut manifest = vec![
+        ("name", "Druid WASM examples".into()),
+        ("short_name", "Druid examples".into()),
+        ("start_url", "./".into()),
+        ("scope", "./".into()),
+        ("display", "standalone".into()),
+        ("background_color", "#ffffff".into()),
+        ("icons", Json::Array(icons)),
+    ];
+    if let Some(color) = &config.theme_color {
+        manifest.push(("theme_color", color.as_str().into()));
+    }
+    Json::object(manifest).to_string()
+}
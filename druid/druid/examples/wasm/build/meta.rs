@@ -0,0 +1,1238 @@
+// Copyright 2020 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Per-example sidecar metadata.
+//!
+//! An example `foo.rs` may be accompanied by a `foo.toml` file in the same directory which
+//! customizes how its web page is generated. The file uses a small subset of TOML: bare or quoted
+//! keys, `[table]` headers, strings (basic, literal and their multi-line forms), integers,
+//! booleans and arrays.
+//!
+//! Some keys can also be given as markers in the leading `//!` doc comment of the example itself,
+//! e.g. `//! tags: animation, input`. Keys in the sidecar take precedence over markers.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::budget::parse_size;
+use crate::config::Config;
+use crate::discover::is_identifier;
+use crate::json::{self, Json};
+
+/// A single value in a metadata file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Value>),
+}
+
+/// The parsed contents of a metadata file. Keys inside `[table]` sections are stored dotted,
+/// e.g. `table.key`.
+pub type Table = BTreeMap<String, Value>;
+
+/// The keys which may be given as `//! key: value` markers in an example's doc comment.
+const DOC_MARKERS: &[&str] = &["tags", "status", "description", "category", "author"];
+
+/// Metadata describing how to generate the page of a single example.
+#[derive(Debug, Clone, Default)]
+pub struct Metadata {
+    /// An html fragment replacing the default `<canvas>` in the page body.
+    pub body: Option<String>,
+    /// Whether the example doesn't draw anything, so its page has an output area instead of the
+    /// canvas.
+    pub non_graphical: bool,
+    /// The plain text title of the page of the example, instead of its entry function.
+    pub title: Option<String>,
+    /// A short plain text description of the example.
+    pub description: Option<String>,
+    /// The first paragraph of the doc comment of the example, with `RCCHAT_DOC_DESCRIPTIONS`.
+    pub long_description: Option<String>,
+    /// The category the example belongs to.
+    pub category: Option<String>,
+    /// Who wrote the example, credited on its page.
+    pub author: Option<String>,
+    /// Tags the index can be filtered by, lowercase and without whitespace.
+    pub tags: Vec<String>,
+    /// The maturity of the example, shown as a badge.
+    pub status: Option<Status>,
+    /// The name of the function exported to JavaScript, instead of the page name.
+    pub entry: Option<String>,
+    /// The argument the entry function is called with, if any.
+    pub argument: Option<Json>,
+    /// An image showing the example. As given in the metadata until it is resolved by
+    /// [`resolve_assets`](Metadata::resolve_assets).
+    pub screenshot: Option<PathBuf>,
+    /// The color mobile browsers tint their interface with on the page of the example.
+    pub theme_color: Option<String>,
+    /// The background of the page of the example, around the canvas.
+    pub background: Option<Background>,
+    /// The size in pixels the page of the example is best shown at, e.g. in an iframe.
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Web features the example needs, checked for before it runs.
+    pub requires: Vec<Feature>,
+    /// Features of this crate the example only compiles with.
+    pub features: Vec<String>,
+    /// The most bytes the page of the example may load, overriding `RCCHAT_EXAMPLE_SIZE_BUDGET`.
+    pub size_budget: Option<u64>,
+    /// Whether the example is left off the index and the gallery, its page still being generated.
+    pub unlisted: bool,
+}
+
+/// How well an example works, shown as a badge on the index and on its page.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Stable,
+    Experimental,
+    /// Known to not work. Examples skipped because they don't build for WASM are shown with this
+    /// status too.
+    Broken,
+}
+
+impl Status {
+    /// Parse a status as given in the `status` key.
+    pub fn from_name(name: &str) -> Option<Status> {
+        match name {
+            "stable" => Some(Status::Stable),
+            "experimental" => Some(Status::Experimental),
+            "broken" => Some(Status::Broken),
+            _ => None,
+        }
+    }
+
+    /// The name of the status, which is also the class of its badge.
+    pub fn name(self) -> &'static str {
+        match self {
+            Status::Stable => "stable",
+            Status::Experimental => "experimental",
+            Status::Broken => "broken",
+        }
+    }
+}
+
+/// A web feature not available in all browsers or on all hardware, which an example may require.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Feature {
+    WebGl,
+    WebGl2,
+    WebGpu,
+}
+
+impl Feature {
+    /// Parse a feature as given in the `requires` key.
+    pub fn from_name(name: &str) -> Option<Feature> {
+        match name {
+            "webgl" => Some(Feature::WebGl),
+            "webgl2" => Some(Feature::WebGl2),
+            "webgpu" => Some(Feature::WebGpu),
+            _ => None,
+        }
+    }
+
+    /// The name of the feature as shown to users.
+    pub fn label(self) -> &'static str {
+        match self {
+            Feature::WebGl => "WebGL",
+            Feature::WebGl2 => "WebGL 2",
+            Feature::WebGpu => "WebGPU",
+        }
+    }
+}
+
+/// The background of an example page, as given in the `background` key.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Background {
+    Color(String),
+    /// A CSS gradient function, such as `linear-gradient(#fff, #ddd)`.
+    Gradient(String),
+    /// The url of an image covering the page.
+    Image(String),
+}
+
+impl Background {
+    /// Parse a background as given in the `background` key: a color, a gradient, or the url of an
+    /// image, optionally wrapped in `url()`. Values which would break the stylesheet of the page
+    /// return `None`.
+    pub fn parse(value: &str) -> Option<Background> {
+        let value = value.trim();
+        if is_color(value) {
+            return Some(Background::Color(value.to_string()));
+        }
+        if let Some(i) = value.find('(') {
+            let gradient = matches!(
+                value[..i].trim_start_matches("repeating-"),
+                "linear-gradient" | "radial-gradient" | "conic-gradient"
+            );
+            if gradient {
+                let mut depth = 0i32;
+                for c in value[i..].chars() {
+                    match c {
+                        '(' => depth += 1,
+                        ')' => depth -= 1,
+                        c if c.is_ascii_alphanumeric() || " .,%/+-#".contains(c) => (),
+                        _ => return None,
+                    }
+                    if depth < 0 {
+                        return None;
+                    }
+                }
+                return Some(Background::Gradient(value.to_string()))
+                    .filter(|_| depth == 0 && value.ends_with(')'));
+            }
+        }
+        let url = match value.strip_prefix("url(") {
+            Some(rest) => rest.strip_suffix(')')?.trim().trim_matches(['"', '\'']),
+            None => value,
+        };
+        let valid = !url.is_empty()
+            && !url
+                .chars()
+                .any(|c| c.is_whitespace() || c.is_control() || "\"'()\\<>".contains(c));
+        Some(Background::Image(url.to_string())).filter(|_| valid)
+    }
+
+    /// The value of the CSS `background` property.
+    pub fn css(&self) -> String {
+        match self {
+            Background::Color(value) | Background::Gradient(value) => value.clone(),
+            Background::Image(url) => format!("url(\"{}\") center / cover no-repeat fixed", url),
+        }
+    }
+}
+
+impl Metadata {
+    /// Load the metadata of the example at `example` from its sidecar, if there is one, and the
+    /// front matter and markers of the doc comment of its `source`.
+    ///
+    /// Panics with a descriptive message if the sidecar exists but is malformed.
+    pub fn load(example: &Path, source: &str) -> Metadata {
+        let markers = doc_markers(source);
+        // Malformed front matter only loses the keys given there, unlike a malformed sidecar.
+        let front = match front_matter(source) {
+            Some(Ok(front)) => front,
+            Some(Err(e)) => {
+                crate::warn(&format!(
+                    "{}: the front matter is ignored, as it is malformed: {}",
+                    example.display(),
+                    e
+                ));
+                Table::new()
+            }
+            None => Table::new(),
+        };
+
+        let sidecar = example.with_extension("toml");
+        let (sidecar_table, origin) = if sidecar.is_file() {
+            crate::rerun_if_changed(&sidecar);
+            let src = fs::read_to_string(&sidecar)
+                .unwrap_or_else(|e| panic!("Failed to read {}: {}", sidecar.display(), e));
+            let table = parse(&src).unwrap_or_else(|e| panic!("{}: {}", sidecar.display(), e));
+            (table, sidecar)
+        } else {
+            (Table::new(), example.to_path_buf())
+        };
+        let combine = |front: &Table| {
+            let mut table = sidecar_table.clone();
+            for (key, value) in front.iter().chain(&markers) {
+                table.entry(key.clone()).or_insert_with(|| value.clone());
+            }
+            table
+        };
+
+        let mut meta = match Metadata::from_table(combine(&front), &origin) {
+            Err(e) if !front.is_empty() => {
+                crate::warn(&format!(
+                    "{}: the front matter is ignored, as it is invalid: {}",
+                    example.display(),
+                    e
+                ));
+                Metadata::from_table(combine(&Table::new()), &origin)
+            }
+            meta => meta,
+        }
+        .unwrap_or_else(|e| panic!("{}: {}", origin.display(), e));
+        // Without a `screenshot` key, a `foo.png` next to the example is its screenshot.
+        if meta.screenshot.is_none() {
+            meta.screenshot = Some(example.with_extension("png")).filter(|p| p.is_file());
+        }
+        meta
+    }
+
+    /// Check that the files referenced by the metadata of the example at `example` exist.
+    ///
+    /// The screenshot is resolved against the assets directory, or against the directory of the
+    /// example if there is none. Images, scripts and other files referenced by relative urls in
+    /// the body, and a background image, are resolved against the directory of the example.
+    ///
+    /// Missing assets are warned about, and a missing screenshot is dropped. In strict mode they
+    /// panic instead.
+    pub fn resolve_assets(&mut self, example: &Path, config: &Config) {
+        let mut missing = Vec::new();
+        if let Some(screenshot) = &self.screenshot {
+            let dir = match &config.assets_dir {
+                Some(dir) => dir.as_path(),
+                None => example.parent().unwrap(),
+            };
+            let path = dir.join(screenshot);
+            if path.is_file() {
+                self.screenshot = Some(path);
+            } else {
+                missing.push(format!("the screenshot {}", path.display()));
+                self.screenshot = None;
+            }
+        }
+        for url in relative_urls(self.body.as_deref().unwrap_or_default()) {
+            let path = example.parent().unwrap().join(url);
+            if !path.exists() {
+                missing.push(format!("{}, referenced by `body`,", path.display()));
+            }
+        }
+        if let Some(Background::Image(url)) = &self.background {
+            if is_relative_url(url) {
+                let path = example
+                    .parent()
+                    .unwrap()
+                    .join(url.split(['?', '#']).next().unwrap());
+                if !path.is_file() {
+                    missing.push(format!("the background {}", path.display()));
+                }
+            }
+        }
+
+        let messages: Vec<String> = missing
+            .iter()
+            .map(|asset| format!("{}: {} does not exist", example.display(), asset))
+            .collect();
+        if config.strict && !messages.is_empty() {
+            panic!("{}", messages.join("\n"));
+        }
+        for msg in messages {
+            crate::warn(&msg);
+        }
+    }
+
+    /// Describe the example by the first paragraph of the doc comment of its `source`, for
+    /// `RCCHAT_DOC_DESCRIPTIONS`. Its first sentence becomes the description unless one is
+    /// given, and the whole paragraph the long description.
+    pub fn describe_from_docs(&mut self, source: &str) {
+        if let Some(paragraph) = doc_paragraph(source) {
+            if self.description.is_none() {
+                self.description = Some(first_sentence(&paragraph).to_string());
+            }
+            self.long_description = Some(paragraph);
+        }
+    }
+
+    /// Build metadata out of a parsed table, validating the known keys.
+    pub fn from_table(mut table: Table, source: &Path) -> Result<Metadata, String> {
+        let non_graphical = match table.remove("graphical") {
+            None => false,
+            Some(Value::Boolean(graphical)) => !graphical,
+            Some(_) => return Err("`graphical` must be a boolean".to_string()),
+        };
+        let mut meta = Metadata {
+            non_graphical,
+            ..Metadata::default()
+        };
+
+        if let Some(body) = take_string(&mut table, "body")? {
+            if body.trim().is_empty() {
+                return Err("`body` must not be empty".to_string());
+            }
+            if !body.contains("id=\"canvas\"") && !meta.non_graphical {
+                crate::warn(&format!(
+                    "{}: `body` has no <canvas id=\"canvas\">, which druid draws into",
+                    source.display()
+                ));
+            }
+            meta.body = Some(body.trim().to_string());
+        }
+
+        meta.title = take_string(&mut table, "title")?
+            .map(|t| t.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|t| !t.is_empty());
+
+        meta.description = take_string(&mut table, "description")?
+            .map(|d| d.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|d| !d.is_empty());
+
+        meta.category = take_string(&mut table, "category")?
+            .map(|c| c.trim().to_string())
+            .filter(|c| !c.is_empty());
+
+        meta.author = take_string(&mut table, "author")?
+            .map(|a| a.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|a| !a.is_empty());
+
+        for tag in take_list(&mut table, "tags")? {
+            let tag = tag.split_whitespace().collect::<Vec<_>>().join("-");
+            let tag = tag.to_lowercase();
+            if !tag.is_empty() && !meta.tags.contains(&tag) {
+                meta.tags.push(tag);
+            }
+        }
+
+        if let Some(status) = take_string(&mut table, "status")? {
+            meta.status = Some(Status::from_name(status.trim()).ok_or_else(|| {
+                format!(
+                    "`status` must be `stable`, `experimental` or `broken`, found `{}`",
+                    status
+                )
+            })?);
+        }
+
+        if let Some(entry) = take_string(&mut table, "entry")? {
+            if !is_identifier(&entry) {
+                return Err(format!("`entry` must be an identifier, found `{}`", entry));
+            }
+            meta.entry = Some(entry);
+        }
+
+        if let Some(argument) = take_string(&mut table, "argument")? {
+            meta.argument = Some(
+                json::parse(&argument)
+                    .map_err(|e| format!("`argument` is not valid JSON: {}", e))?,
+            );
+        }
+
+        if let Some(screenshot) = take_string(&mut table, "screenshot")? {
+            let path = PathBuf::from(screenshot.trim());
+            if image_type(&path).is_none() {
+                return Err(format!(
+                    "`screenshot` {} must be a png, jpeg, gif, webp or svg image",
+                    path.display()
+                ));
+            }
+            meta.screenshot = Some(path);
+        }
+
+        for name in take_list(&mut table, "requires")? {
+            let feature = Feature::from_name(name.trim()).ok_or_else(|| {
+                format!(
+                    "`requires` must only contain `webgl`, `webgl2` or `webgpu`, found `{}`",
+                    name
+                )
+            })?;
+            if !meta.requires.contains(&feature) {
+                meta.requires.push(feature);
+            }
+        }
+
+        for feature in take_list(&mut table, "features")? {
+            let feature = feature.trim().to_string();
+            let valid = !feature.is_empty()
+                && feature
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !valid {
+                return Err(format!(
+                    "`features` must only contain feature names, found `{}`",
+                    feature
+                ));
+            }
+            if !meta.features.contains(&feature) {
+                meta.features.push(feature);
+            }
+        }
+
+        meta.size_budget = match table.remove("size_budget") {
+            None => None,
+            Some(Value::Integer(size)) if size >= 0 => Some(size as u64),
+            Some(Value::String(size)) => {
+                Some(parse_size(&size).map_err(|e| format!("`size_budget`: {}", e))?)
+            }
+            Some(_) => return Err("`size_budget` must be a size in bytes".to_string()),
+        };
+
+        for (key, size) in [("width", &mut meta.width), ("height", &mut meta.height)] {
+            *size = match table.remove(key) {
+                None => None,
+                Some(Value::Integer(n)) if n > 0 && n <= i64::from(u32::MAX) => Some(n as u32),
+                Some(_) => return Err(format!("`{}` must be a positive number of pixels", key)),
+            };
+        }
+
+        meta.unlisted = match table.remove("unlisted") {
+            None => false,
+            Some(Value::Boolean(unlisted)) => unlisted,
+            Some(_) => return Err("`unlisted` must be a boolean".to_string()),
+        };
+
+        if let Some(color) = take_string(&mut table, "theme_color")? {
+            let color = color.trim();
+            if is_color(color) {
+                meta.theme_color = Some(color.to_string());
+            } else {
+                crate::warn(&format!(
+                    "{}: `theme_color` `{}` is not a color, it is ignored",
+                    source.display(),
+                    color
+                ));
+            }
+        }
+
+        if let Some(background) = take_string(&mut table, "background")? {
+            meta.background = Background::parse(&background);
+            if meta.background.is_none() {
+                crate::warn(&format!(
+                    "{}: `background` `{}` is not a color, gradient or image url, it is ignored",
+                    source.display(),
+                    background.trim()
+                ));
+            }
+        }
+
+        for key in table.keys() {
+            crate::warn(&format!("{}: unknown key `{}`", source.display(), key));
+        }
+
+        Ok(meta)
+    }
+}
+
+/// The relative urls in the `src` and `href` attributes of the html `body`, without their query or
+/// fragment. Urls with a scheme and urls starting with `/` or `#` are left out.
+pub fn relative_urls(body: &str) -> Vec<&str> {
+    let mut urls = Vec::new();
+    for attr in &["src=", "href="] {
+        for (i, _) in body.match_indices(attr) {
+            // Skip e.g. `data-src=`.
+            let preceding = body[..i].chars().next_back();
+            if !matches!(preceding, Some(c) if c.is_whitespace()) {
+                continue;
+            }
+            let rest = &body[i + attr.len()..];
+            let quote = match rest.chars().next() {
+                Some(quote @ '"') | Some(quote @ '\'') => quote,
+                _ => continue,
+            };
+            let value = match rest[1..].find(quote) {
+                Some(end) => &rest[1..end + 1],
+                None => continue,
+            };
+            let url = value.split(['?', '#']).next().unwrap().trim();
+            if !url.is_empty() && is_relative_url(url) {
+                urls.push(url);
+            }
+        }
+    }
+    urls
+}
+
+/// Whether `url` has neither a scheme nor starts with `/` or `#`.
+fn is_relative_url(url: &str) -> bool {
+    let scheme = url.find(':').is_some_and(|i| {
+        url[..i]
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+    });
+    !scheme && !url.starts_with(['/', '#'])
+}
+
+/// The file extension of the image at `path` if it is of a type browsers display, lowercased.
+pub fn image_type(path: &Path) -> Option<String> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    match ext.as_str() {
+        "png" | "jpg" | "jpeg" | "gif" | "webp" | "svg" => Some(ext),
+        _ => None,
+    }
+}
+
+/// Whether `color` is a CSS color: a hex color such as `#1e90ff`, a color keyword or a color
+/// function such as `rgb(30 144 255)`.
+pub fn is_color(color: &str) -> bool {
+    if let Some(hex) = color.strip_prefix('#') {
+        return [3, 4, 6, 8].contains(&hex.len()) && hex.chars().all(|c| c.is_ascii_hexdigit());
+    }
+    match color.find('(') {
+        Some(i) => {
+            let args = &color[i + 1..];
+            [
+                "rgb", "rgba", "hsl", "hsla", "hwb", "lab", "lch", "oklab", "oklch",
+            ]
+            .contains(&&color[..i])
+                && args.ends_with(')')
+                && args[..args.len() - 1]
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || " .,%/+-".contains(c))
+        }
+        None => !color.is_empty() && color.chars().all(|c| c.is_ascii_alphabetic()),
+    }
+}
+
+/// Remove `key` from `table`, requiring it to be a string if present.
+fn take_string(table: &mut Table, key: &str) -> Result<Option<String>, String> {
+    match table.remove(key) {
+        None => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s)),
+        Some(_) => Err(format!("`{}` must be a string", key)),
+    }
+}
+
+/// Remove `key` from `table`, requiring it to be an array of strings or a comma separated string
+/// if present.
+fn take_list(table: &mut Table, key: &str) -> Result<Vec<String>, String> {
+    match table.remove(key) {
+        None => Ok(Vec::new()),
+        Some(Value::String(s)) => Ok(s.split(',').map(|s| s.trim().to_string()).collect()),
+        Some(Value::Array(items)) => items
+            .into_iter()
+            .map(|item| match item {
+                Value::String(s) => Ok(s),
+                _ => Err(format!("`{}` must only contain strings", key)),
+            })
+            .collect(),
+        Some(_) => Err(format!("`{}` must be an array of strings", key)),
+    }
+}
+
+/// Collect the `//! key: value` markers of the leading doc comment in `source`.
+///
+/// Only keys listed in [`DOC_MARKERS`] are recognized, so that regular documentation which happens
+/// to contain a colon is left alone. Values are returned as strings.
+fn doc_markers(source: &str) -> Table {
+    let mut markers = Table::new();
+    for line in source.lines().map(str::trim) {
+        let doc = match line.strip_prefix("//!") {
+            Some(doc) => doc.trim(),
+            // Skip the license header and other comments preceding the doc comment.
+            None if line.is_empty() || line.starts_with("//") => continue,
+            None => break,
+        };
+        if let Some(i) = doc.find(':') {
+            let key = doc[..i].trim();
+            if DOC_MARKERS.contains(&key) {
+                let value = Value::String(doc[i + 1..].trim().to_string());
+                markers.entry(key.to_string()).or_insert(value);
+            }
+        }
+    }
+    markers
+}
+
+/// The fence opening the front matter of an example.
+const FRONT_MATTER: &str = "```toml";
+
+/// Parse the front matter of the leading doc comment in `source`, a block of `//!` lines fenced
+/// by ```` ```toml ```` and ```` ``` ````, if it has one.
+fn front_matter(source: &str) -> Option<Result<Table, String>> {
+    let mut lines = Vec::new();
+    let mut inside = false;
+    for line in source.lines().map(str::trim) {
+        let doc = match line.strip_prefix("//!") {
+            Some(doc) => doc,
+            None if line.is_empty() || line.starts_with("//") => continue,
+            None => break,
+        };
+        if !inside {
+            inside = doc.trim() == FRONT_MATTER;
+        } else if doc.trim() == "```" {
+            return Some(parse(&lines.join("\n")));
+        } else {
+            lines.push(doc.strip_prefix(' ').unwrap_or(doc));
+        }
+    }
+    if inside {
+        Some(Err(format!("its `{}` block is never closed", FRONT_MATTER)))
+    } else {
+        None
+    }
+}
+
+/// The first paragraph of the leading doc comment in `source`, given as `//!` lines or as a
+/// `/*! */` block, joined into a single line. Marker lines and fenced blocks, such as the front
+/// matter, are left out.
+fn doc_paragraph(source: &str) -> Option<String> {
+    let mut words = Vec::new();
+    let mut in_block = false;
+    let mut in_fence = false;
+    for line in source.lines().map(str::trim) {
+        let (doc, ends) = if in_block {
+            match line.find("*/") {
+                Some(i) => (&line[..i], true),
+                None => (line, false),
+            }
+        } else if let Some(rest) = line.strip_prefix("/*!") {
+            in_block = true;
+            match rest.find("*/") {
+                Some(i) => (&rest[..i], true),
+                None => (rest, false),
+            }
+        } else if let Some(doc) = line.strip_prefix("//!") {
+            (doc, false)
+        } else if words.is_empty() && (line.is_empty() || line.starts_with("//")) {
+            // Skip the license header and other comments preceding the doc comment.
+            continue;
+        } else {
+            break;
+        };
+        // The lines of block comments often start with a `*`.
+        let doc = if in_block {
+            doc.trim().trim_start_matches('*').trim()
+        } else {
+            doc.trim()
+        };
+        in_block &= !ends;
+
+        if doc.starts_with("```") {
+            if !words.is_empty() {
+                break;
+            }
+            in_fence = !in_fence;
+            continue;
+        } else if in_fence {
+            continue;
+        }
+        let marker = doc
+            .find(':')
+            .is_some_and(|i| DOC_MARKERS.contains(&doc[..i].trim()));
+        if doc.is_empty() && !words.is_empty() {
+            break;
+        } else if !marker {
+            words.extend(doc.split_whitespace());
+        }
+        if ends && !words.is_empty() {
+            break;
+        }
+    }
+    Some(words.join(" ")).filter(|paragraph| !paragraph.is_empty())
+}
+
+/// The first sentence of `text`, which ends at a `.`, `!` or `?` followed by a capitalized word,
+/// so that e.g. "e.g. the" doesn't end it.
+fn first_sentence(text: &str) -> &str {
+    for (i, c) in text.char_indices() {
+        if matches!(c, '.' | '!' | '?') {
+            let mut rest = text[i + 1..].chars();
+            if rest.next() == Some(' ') && rest.next().is_some_and(char::is_uppercase) {
+                return &text[..=i];
+            }
+        }
+    }
+    text
+}
+
+/// Parse the TOML subset supported by metadata files.
+pub fn parse(src: &str) -> Result<Table, String> {
+    let mut parser = Parser {
+        chars: src.chars().collect(),
+        pos: 0,
+        line: 1,
+    };
+    parser
+        .parse_document()
+        .map_err(|e| format!("line {}: {}", parser.line, e))
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        s.chars()
+            .enumerate()
+            .all(|(i, c)| self.chars.get(self.pos + i) == Some(&c))
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        match self.bump() {
+            Some(found) if found == c => Ok(()),
+            Some(found) => Err(format!("expected `{}`, found `{}`", c, found)),
+            None => Err(format!("expected `{}`, found end of file", c)),
+        }
+    }
+
+    /// Skip spaces and tabs.
+    fn skip_blank(&mut self) {
+        while let Some(' ') | Some('\t') = self.peek() {
+            self.bump();
+        }
+    }
+
+    /// Skip a comment, if there is one, up to (but not including) the end of the line.
+    fn skip_comment(&mut self) {
+        if self.peek() == Some('#') {
+            while !matches!(self.peek(), None | Some('\n')) {
+                self.bump();
+            }
+        }
+    }
+
+    /// Skip whitespace, newlines and comments.
+    fn skip_all(&mut self) {
+        loop {
+            self.skip_blank();
+            self.skip_comment();
+            match self.peek() {
+                Some('\n') | Some('\r') => {
+                    self.bump();
+                }
+                _ => return,
+            }
+        }
+    }
+
+    /// Require the rest of the line to be empty or a comment.
+    fn end_of_line(&mut self) -> Result<(), String> {
+        self.skip_blank();
+        self.skip_comment();
+        if self.peek() == Some('\r') {
+            self.bump();
+        }
+        match self.bump() {
+            None | Some('\n') => Ok(()),
+            Some(c) => Err(format!("unexpected `{}` after value", c)),
+        }
+    }
+
+    fn parse_document(&mut self) -> Result<Table, String> {
+        let mut table = Table::new();
+        let mut prefix = String::new();
+        loop {
+            self.skip_all();
+            match self.peek() {
+                None => return Ok(table),
+                Some('[') => {
+                    self.bump();
+                    self.skip_blank();
+                    prefix = self.parse_key()?;
+                    self.skip_blank();
+                    self.expect(']')?;
+                    self.end_of_line()?;
+                }
+                Some(_) => {
+                    let key = self.parse_key()?;
+                    let key = if prefix.is_empty() {
+                        key
+                    } else {
+                        format!("{}.{}", prefix, key)
+                    };
+                    self.skip_blank();
+                    self.expect('=')?;
+                    self.skip_blank();
+                    let value = self.parse_value()?;
+                    if table.insert(key.clone(), value).is_some() {
+                        return Err(format!("duplicate key `{}`", key));
+                    }
+                    self.end_of_line()?;
+                }
+            }
+        }
+    }
+
+    /// Parse a possibly dotted key, returning its parts joined with `.`.
+    fn parse_key(&mut self) -> Result<String, String> {
+        let mut parts = Vec::new();
+        loop {
+            self.skip_blank();
+            let part = match self.peek() {
+                Some('"') => self.parse_basic_string()?,
+                Some('\'') => self.parse_literal_string()?,
+                _ => {
+                    let start = self.pos;
+                    while let Some(c) = self.peek() {
+                        if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                            self.bump();
+                        } else {
+                            break;
+                        }
+                    }
+                    if start == self.pos {
+                        return Err("expected a key".to_string());
+                    }
+                    self.chars[start..self.pos].iter().collect()
+                }
+            };
+            parts.push(part);
+            self.skip_blank();
+            if self.peek() == Some('.') {
+                self.bump();
+            } else {
+                return Ok(parts.join("."));
+            }
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Value, String> {
+        if self.starts_with("\"\"\"") {
+            return self.parse_multiline_basic_string().map(Value::String);
+        }
+        if self.starts_with("'''") {
+            return self.parse_multiline_literal_string().map(Value::String);
+        }
+        match self.peek() {
+            Some('"') => self.parse_basic_string().map(Value::String),
+            Some('\'') => self.parse_literal_string().map(Value::String),
+            Some('[') => self.parse_array(),
+            Some('t') | Some('f') => self.parse_boolean(),
+            Some(c) if c.is_ascii_digit() || c == '-' || c == '+' => self.parse_integer(),
+            Some(c) => Err(format!("unexpected `{}`, expected a value", c)),
+            None => Err("expected a value, found end of file".to_string()),
+        }
+    }
+
+    fn parse_boolean(&mut self) -> Result<Value, String> {
+        if self.starts_with("true") {
+            self.pos += 4;
+            Ok(Value::Boolean(true))
+        } else if self.starts_with("false") {
+            self.pos += 5;
+            Ok(Value::Boolean(false))
+        } else {
+            Err("expected `true` or `false`".to_string())
+        }
+    }
+
+    fn parse_integer(&mut self) -> Result<Value, String> {
+        let start = self.pos;
+        if let Some('-') | Some('+') = self.peek() {
+            self.bump();
+        }
+        while let Some(c) = self.peek() {
+            if c.is_ascii_digit() || c == '_' {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        let text: String = self.chars[start..self.pos]
+            .iter()
+            .filter(|c| **c != '_')
+            .collect();
+        text.parse()
+            .map(Value::Integer)
+            .map_err(|_| format!("invalid integer `{}`", text))
+    }
+
+    fn parse_array(&mut self) -> Result<Value, String> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        loop {
+            self.skip_all();
+            if self.peek() == Some(']') {
+                self.bump();
+                return Ok(Value::Array(items));
+            }
+            items.push(self.parse_value()?);
+            self.skip_all();
+            match self.bump() {
+                Some(',') => (),
+                Some(']') => return Ok(Value::Array(items)),
+                Some(c) => return Err(format!("unexpected `{}` in array", c)),
+                None => return Err("unterminated array".to_string()),
+            }
+        }
+    }
+
+    fn parse_escape(&mut self) -> Result<char, String> {
+        match self.bump() {
+            Some('n') => Ok('\n'),
+            Some('t') => Ok('\t'),
+            Some('r') => Ok('\r'),
+            Some('"') => Ok('"'),
+            Some('\\') => Ok('\\'),
+            Some(u @ 'u') | Some(u @ 'U') => {
+                let len = if u == 'u' { 4 } else { 8 };
+                let mut code = String::new();
+                for _ in 0..len {
+                    code.extend(self.bump());
+                }
+                u32::from_str_radix(&code, 16)
+                    .ok()
+                    .and_then(std::char::from_u32)
+                    .ok_or_else(|| format!("invalid unicode escape `\\{}{}`", u, code))
+            }
+            Some(c) => Err(format!("invalid escape `\\{}`", c)),
+            None => Err("unterminated string".to_string()),
+        }
+    }
+
+    fn parse_basic_string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(s),
+                Some('\\') => s.push(self.parse_escape()?),
+                Some('\n') | None => return Err("unterminated string".to_string()),
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    fn parse_literal_string(&mut self) -> Result<String, String> {
+        self.expect('\'')?;
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                Some('\'') => return Ok(s),
+                Some('\n') | None => return Err("unterminated string".to_string()),
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    /// Skip the newline directly following the opening delimiter of a multi-line string.
+    fn skip_leading_newline(&mut self) {
+        if self.starts_with("\r\n") {
+            self.bump();
+        }
+        if self.peek() == Some('\n') {
+            self.bump();
+        }
+    }
+
+    fn parse_multiline_basic_string(&mut self) -> Result<String, String> {
+        self.pos += 3;
+        self.skip_leading_newline();
+        let mut s = String::new();
+        loop {
+            if self.starts_with("\"\"\"") {
+                self.pos += 3;
+                return Ok(s);
+            }
+            match self.bump() {
+                Some('\\') => {
+                    // A backslash at the end of a line trims the line break and any whitespace
+                    // up to the next non-whitespace character.
+                    if let Some('\n') | Some('\r') | Some(' ') | Some('\t') = self.peek() {
+                        while let Some(c) = self.peek() {
+                            if c.is_whitespace() {
+                                self.bump();
+                            } else {
+                                break;
+                            }
+                        }
+                    } else {
+                        s.push(self.parse_escape()?);
+                    }
+                }
+                Some(c) => s.push(c),
+                None => return Err("unterminated string".to_string()),
+            }
+        }
+    }
+
+    fn parse_multiline_literal_string(&mut self) -> Result<String, String> {
+        self.pos += 3;
+        self.skip_leading_newline();
+        let mut s = String::new();
+        loop {
+            if self.starts_with("'''") {
+                self.pos += 3;
+                return Ok(s);
+            }
+            match self.bump() {
+                Some(c) => s.push(c),
+                None => return Err("unterminated string".to_string()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_assets_of_the_body() {
+        let body = concat!(
+            r#"<img src="logo.png?v=2" alt=""><script src='../shared/app.js'></script>"#,
+            r##"<a href="https://example.org">x</a><a href="#top">top</a><img data-src="lazy.png">"##,
+            r#"<link href="/style.css"><img src="data:image/png;base64,AA"><a href="notes.html#a">"#,
+        );
+        assert_eq!(
+            relative_urls(body),
+            vec!["logo.png", "../shared/app.js", "notes.html"]
+        );
+    }
+
+    #[test]
+    fn describes_examples_by_their_docs() {
+        let source = "// Copyright\n\n//! tags: layout\n//! Lays out widgets, e.g. in rows. Also\n\
+                      //! columns.\n//!\n//! More details.\nuse druid::*;\n";
+        let mut meta = Metadata::default();
+        meta.describe_from_docs(source);
+        assert_eq!(
+            meta.description.as_deref(),
+            Some("Lays out widgets, e.g. in rows.")
+        );
+        assert_eq!(
+            meta.long_description.as_deref(),
+            Some("Lays out widgets, e.g. in rows. Also columns.")
+        );
+
+        let source = "/*!\n * A <b>bold</b>\n * claim.\n *\n * Details.\n */\nfn main() {}\n";
+        assert_eq!(
+            doc_paragraph(source).as_deref(),
+            Some("A <b>bold</b> claim.")
+        );
+        assert_eq!(
+            doc_paragraph("/*! One line. */"),
+            Some("One line.".to_string())
+        );
+        assert_eq!(doc_paragraph("use druid::*;\n//! Too late.\n"), None);
+    }
+
+    #[test]
+    fn reads_front_matter() {
+        let source =
+            "//! ```toml\n//! title = \"Calculator\"\n//! tags = [\"input\"]\n//! width = 400\n\
+                      //! ```\n//!\n//! A simple calculator.\nuse druid::*;\n";
+        let front = front_matter(source).unwrap().unwrap();
+        assert_eq!(front["title"], Value::String("Calculator".to_string()));
+        assert_eq!(front["width"], Value::Integer(400));
+        assert_eq!(
+            doc_paragraph(source).as_deref(),
+            Some("A simple calculator.")
+        );
+
+        assert!(
+            front_matter("//! ```toml\n//! title = \"Calculator\n//! ```\n")
+                .unwrap()
+                .is_err()
+        );
+        assert!(front_matter("//! ```toml\n//! title = \"Calculator\"\n")
+            .unwrap()
+            .is_err());
+        assert!(front_matter("//! A simple calculator.\n").is_none());
+    }
+
+    #[test]
+    fn parses_the_toml_subset() {
+        let src = concat!(
+            "# A comment\n",
+            "title = \"Tab\\t\\\"quoted\\\" \\u00e9\\U0001F600\\\\\" # trailing comment\n",
+            "path = 'C:\\raw\\# not a comment'\n",
+            "width = 1_024\n",
+            "offset = -3\n",
+            "unlisted = true\n",
+            "tags = [\n  \"a\", # first\n  'b',\n]\n",
+            "nested = [[1, 2], [], [false]]\n",
+            "long = \"\"\"\nline one\nline \\\n    two\"\"\"\n",
+            "raw = '''\n\\n stays'''\r\n",
+            "\n",
+            "[page]\n",
+            "\"quoted key\" = 1\n",
+            "dotted.key = 2\n",
+        );
+        let table = parse(src).unwrap();
+        let string = |s: &str| Value::String(s.to_string());
+        assert_eq!(table["title"], string("Tab\t\"quoted\" \u{e9}\u{1F600}\\"));
+        assert_eq!(table["path"], string("C:\\raw\\# not a comment"));
+        assert_eq!(table["width"], Value::Integer(1024));
+        assert_eq!(table["offset"], Value::Integer(-3));
+        assert_eq!(table["unlisted"], Value::Boolean(true));
+        assert_eq!(table["tags"], Value::Array(vec![string("a"), string("b")]));
+        assert_eq!(
+            table["nested"],
+            Value::Array(vec![
+                Value::Array(vec![Value::Integer(1), Value::Integer(2)]),
+                Value::Array(vec![]),
+                Value::Array(vec![Value::Boolean(false)]),
+            ])
+        );
+        assert_eq!(table["long"], string("line one\nline two"));
+        assert_eq!(table["raw"], string("\\n stays"));
+        assert_eq!(table["page.quoted key"], Value::Integer(1));
+        assert_eq!(table["page.dotted.key"], Value::Integer(2));
+        assert_eq!(table.len(), 11);
+        assert_eq!(parse("").unwrap(), Table::new());
+    }
+
+    #[test]
+    fn rejects_malformed_toml() {
+        let err = |src| parse(src).unwrap_err();
+        assert_eq!(err("a = 1\na = 2\n"), "line 2: duplicate key `a`");
+        assert_eq!(
+            err("[page]\nx = 1\n[page]\nx = 2"),
+            "line 4: duplicate key `page.x`"
+        );
+        assert_eq!(err("title = \"open\n"), "line 2: unterminated string");
+        assert_eq!(err("title = '''open"), "line 1: unterminated string");
+        assert_eq!(err("title = \"\\q\""), "line 1: invalid escape `\\q`");
+        assert_eq!(
+            err("title = \"\\uD800\""),
+            "line 1: invalid unicode escape `\\uD800`"
+        );
+        assert_eq!(err("width = 1 2"), "line 1: unexpected `2` after value");
+        assert_eq!(err("width = 12px"), "line 1: unexpected `p` after value");
+        assert_eq!(
+            err("width = 99999999999999999999"),
+            "line 1: invalid integer `99999999999999999999`"
+        );
+        assert_eq!(err("flag = tru"), "line 1: expected `true` or `false`");
+        assert_eq!(err("= 1"), "line 1: expected a key");
+        assert_eq!(err("a 1"), "line 1: expected `=`, found `1`");
+        assert_eq!(err("a ="), "line 1: expected a value, found end of file");
+        assert_eq!(
+            err("tags = [\"a\" \"b\"]"),
+            "line 1: unexpected `\"` in array"
+        );
+        assert_eq!(
+            err("tags = [\"a\","),
+            "line 1: expected a value, found end of file"
+        );
+        assert_eq!(err("[page"), "line 1: expected `]`, found end of file");
+        // Inline tables and floats aren't part of the subset.
+        assert_eq!(
+            err("size = { width = 1 }"),
+            "line 1: unexpected `{`, expected a value"
+        );
+        assert_eq!(err("scale = 1.5"), "line 1: unexpected `.` after value");
+    }
+
+    #[test]
+    fn parses_backgrounds() {
+        let parse = |value| Background::parse(value).map(|b| b.css());
+        assert_eq!(parse(" #223 "), Some("#223".to_string()));
+        assert_eq!(
+            parse("repeating-linear-gradient(45deg, rgb(0 0 0 / 50%) 0 10px, #fff 10px 20px)"),
+            Some(
+                "repeating-linear-gradient(45deg, rgb(0 0 0 / 50%) 0 10px, #fff 10px 20px)".into()
+            )
+        );
+        assert_eq!(
+            parse("url('img/sky.jpg')"),
+            Some("url(\"img/sky.jpg\") center / cover no-repeat fixed".to_string())
+        );
+        assert_eq!(
+            parse("https://example.org/sky.jpg"),
+            Some("url(\"https://example.org/sky.jpg\") center / cover no-repeat fixed".into())
+        );
+        assert_eq!(parse("linear-gradient(red, blue"), None);
+        assert_eq!(parse("linear-gradient(red, blue)); color: red"), None);
+        assert_eq!(parse("sky.jpg\"); } body { color: red"), None);
+        assert_eq!(parse("url(a.png"), None);
+    }
+}
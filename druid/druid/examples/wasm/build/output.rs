@@ -0,0 +1,333 @@
+// Copyright 2020 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Writing of generated files.
+//!
+//! Generated files land in the source tree, so every file written is recorded together with a
+//! checksum of its contents in a ledger in `OUT_DIR`. On the next build, a generated file whose
+//! contents no longer match the recorded checksum must have been edited by hand, which is reported
+//! before the file is overwritten.
+//!
+//! Files whose contents wouldn't change are left untouched, so that their modification time only
+//! changes when they do. Files recorded by the previous build which are not generated anymore are
+//! removed. Files listed in the `.keep` file of the crate are neither overwritten nor removed.
+//!
+//! Files are replaced atomically, by writing a temporary file next to them and renaming it over
+//! them, so that an interrupted build leaves either the old or the new contents behind, never a
+//! truncated file. Read-only files, e.g. left behind by vendoring, are made writable first.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ffi::OsString;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+use crate::compress::gzip;
+use crate::config::Config;
+
+/// The name of the ledger file in `OUT_DIR`.
+const LEDGER: &str = "generated.sums";
+
+/// The key of the ledger lines recording an example, instead of the checksum of a file.
+const EXAMPLE: &str = "example";
+
+/// Writes generated files and keeps track of them in the ledger.
+pub struct Output {
+    ledger: PathBuf,
+    previous: HashMap<PathBuf, u64>,
+    written: BTreeMap<PathBuf, u64>,
+    /// Whether to write a gzip compressed copy next to each page.
+    gzip: bool,
+    /// Files listed in `.keep`, which are left alone.
+    keep: HashSet<PathBuf>,
+    /// The owner written files are given to.
+    owner: Option<(u32, u32)>,
+    /// The examples of the previous build, if there was one.
+    previous_examples: Option<BTreeSet<String>>,
+    /// The examples of this build.
+    examples: BTreeSet<String>,
+}
+
+impl Output {
+    /// Create a writer, loading the ledger of the previous build from `out_dir` if there is one.
+    pub fn new(out_dir: &Path, config: &Config) -> Output {
+        let ledger = out_dir.join(LEDGER);
+        let (previous, previous_examples) = match fs::read_to_string(&ledger) {
+            Ok(ledger) => {
+                let (files, examples) = parse_ledger(&ledger);
+                // Ledgers of older builds don't record the examples.
+                (
+                    files,
+                    Some(examples).filter(|examples| !examples.is_empty()),
+                )
+            }
+            Err(_) => (HashMap::new(), None),
+        };
+        Output {
+            ledger,
+            previous,
+            previous_examples,
+            examples: BTreeSet::new(),
+            written: BTreeMap::new(),
+            gzip: config.gzip,
+            keep: config.keep.iter().cloned().collect(),
+            owner: config.owner,
+        }
+    }
+
+    /// Write the html page `contents` to `path`, along with a precompressed copy if enabled.
+    pub fn write_page(&mut self, path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+        let contents = contents.as_ref();
+        if self.gzip {
+            self.write(&gz_path(path), gzip(contents))?;
+        }
+        self.write(path, contents)
+    }
+
+    /// Like [`retain`](Output::retain), for a page written by [`write_page`](Output::write_page).
+    pub fn retain_page(&mut self, path: &Path) {
+        if self.gzip {
+            self.retain(&gz_path(path));
+        }
+        self.retain(path);
+    }
+
+    /// Write `contents` to `path` unless it already has them, creating any missing parent
+    /// directories.
+    ///
+    /// Warns if the file was modified since it was generated by the previous build. Files listed in
+    /// `.keep` are not written at all.
+    pub fn write(&mut self, path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+        if self.keep.contains(path) {
+            crate::warn(&format!(
+                "{} is listed in .keep, so it was not regenerated",
+                path.display()
+            ));
+            return Ok(());
+        }
+        let contents = contents.as_ref();
+        let sum = checksum(contents);
+        self.written.insert(path.to_path_buf(), sum);
+
+        let current = fs::read(path).ok().map(|current| checksum(&current));
+        if current == Some(sum) {
+            crate::set_owner(path, self.owner);
+            return Ok(());
+        }
+        if let (Some(recorded), Some(current)) = (self.previous.get(path), current) {
+            if current != *recorded {
+                crate::warn(&format!(
+                    "{} was edited by hand and has been overwritten; \
+                     change the build script instead",
+                    path.display()
+                ));
+            }
+        }
+        if let Some(parent) = path.parent() {
+            if !parent.exists() {
+                fs::create_dir_all(parent)?;
+                crate::set_owner(parent, self.owner);
+            }
+        }
+        if fs::metadata(path).is_ok_and(|m| m.permissions().readonly()) {
+            crate::warn(&format!(
+                "{} was read-only, it has been made writable to regenerate it",
+                path.display()
+            ));
+            make_writable(path).map_err(|e| unwritable(path, e))?;
+        }
+        replace(path, contents).map_err(|e| unwritable(path, e))?;
+        crate::set_owner(path, self.owner);
+        Ok(())
+    }
+
+    /// The paths of the files generated by this build so far, including retained ones, in order.
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.written.keys().map(PathBuf::as_path)
+    }
+
+    /// The number of files generated by this build so far, including retained ones.
+    pub fn file_count(&self) -> usize {
+        self.written.len()
+    }
+
+    /// Whether `path` is listed in `.keep`, and thus never written.
+    pub fn is_kept(&self, path: &Path) -> bool {
+        self.keep.contains(path)
+    }
+
+    /// A checksum of all files written so far, which changes whenever any of them does.
+    pub fn fingerprint(&self) -> u64 {
+        let sums: Vec<u8> = self
+            .written
+            .values()
+            .flat_map(|sum| sum.to_le_bytes().to_vec())
+            .collect();
+        checksum(&sums)
+    }
+
+    /// The module paths of the examples recorded by the previous build, or `None` if there was no
+    /// previous build recording them.
+    pub fn previous_examples(&self) -> Option<&BTreeSet<String>> {
+        self.previous_examples.as_ref()
+    }
+
+    /// Record the module paths of the examples of this build in the ledger.
+    pub fn record_examples(&mut self, examples: BTreeSet<String>) {
+        self.examples = examples;
+    }
+
+    /// Keep the record of a file generated by a previous build which is deliberately not
+    /// regenerated by this one.
+    pub fn retain(&mut self, path: &Path) {
+        if let Some(sum) = self.previous.get(path) {
+            self.written.insert(path.to_path_buf(), *sum);
+        }
+    }
+
+    /// Remove the files generated by the previous build but not by this one, and record the files
+    /// written by this build in the ledger.
+    pub fn finish(self) -> Result<()> {
+        for (path, recorded) in &self.previous {
+            if self.written.contains_key(path) || self.keep.contains(path) {
+                continue;
+            }
+            match fs::read(path) {
+                Ok(current) if checksum(&current) == *recorded => fs::remove_file(path)?,
+                Ok(_) => crate::warn(&format!(
+                    "{} is no longer generated, but was edited by hand so it is kept",
+                    path.display()
+                )),
+                Err(_) => (),
+            }
+        }
+
+        let mut ledger: String = self
+            .written
+            .iter()
+            .map(|(path, sum)| format!("{:016x} {}\n", sum, path.display()))
+            .collect();
+        for example in &self.examples {
+            ledger.push_str(&format!("{} {}\n", EXAMPLE, example));
+        }
+        replace(&self.ledger, ledger)
+    }
+}
+
+/// Let the owner of `path` write to it.
+fn make_writable(path: &Path) -> Result<()> {
+    let mut permissions = fs::metadata(path)?.permissions();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        permissions.set_mode(permissions.mode() | 0o200);
+    }
+    #[cfg(not(unix))]
+    #[allow(clippy::permissions_set_readonly_false)]
+    permissions.set_readonly(false);
+    fs::set_permissions(path, permissions)
+}
+
+/// Explain how to fix the failure to write the generated file `path` for lack of permissions.
+fn unwritable(path: &Path, e: Error) -> Error {
+    if e.kind() != ErrorKind::PermissionDenied {
+        return e;
+    }
+    Error::new(
+        e.kind(),
+        format!(
+            "{} can't be written ({}); make it and its directory writable by this build, \
+             or list it in .keep to leave it alone",
+            path.display(),
+            e
+        ),
+    )
+}
+
+/// Write `contents` to `path` atomically, by writing them to a temporary file in the same
+/// directory first and renaming it to `path`.
+///
+/// A rename within one directory replaces the destination atomically on unix, and on Windows
+/// `fs::rename` replaces an existing destination as well. The permissions of an existing file are
+/// kept.
+pub fn replace(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(".{}.tmp", std::process::id()));
+    let temp = path.with_file_name(name);
+    let result = fs::write(&temp, contents)
+        .and_then(|()| match fs::metadata(path) {
+            Ok(metadata) => fs::set_permissions(&temp, metadata.permissions()),
+            Err(_) => Ok(()),
+        })
+        .and_then(|()| fs::rename(&temp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// The path of the gzip compressed copy of `path`, e.g. `index.html.gz`.
+fn gz_path(path: &Path) -> PathBuf {
+    let mut gz = OsString::from(path.as_os_str());
+    gz.push(".gz");
+    PathBuf::from(gz)
+}
+
+/// Parse the ledger into the checksums of the files and the module paths of the examples.
+fn parse_ledger(ledger: &str) -> (HashMap<PathBuf, u64>, BTreeSet<String>) {
+    let mut files = HashMap::new();
+    let mut examples = BTreeSet::new();
+    for line in ledger.lines() {
+        let mut parts = line.splitn(2, ' ');
+        let (key, value) = match (parts.next(), parts.next()) {
+            (Some(key), Some(value)) => (key, value),
+            _ => continue,
+        };
+        if key == EXAMPLE {
+            examples.insert(value.to_string());
+        } else if let Ok(sum) = u64::from_str_radix(key, 16) {
+            files.insert(PathBuf::from(value), sum);
+        }
+    }
+    (files, examples)
+}
+
+/// A 64-bit FNV-1a hash of `bytes`. This only needs to detect edits, not tampering.
+pub fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_files_without_leaving_temporary_ones() {
+        let dir = crate::fixture_dir("replace");
+        let path = dir.join("index.html");
+
+        replace(&path, "old").unwrap();
+        replace(&path, "new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        // A failed write leaves the destination alone.
+        fs::create_dir(dir.join("html")).unwrap();
+        assert!(replace(&dir.join("html"), "page").is_err());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+    }
+}
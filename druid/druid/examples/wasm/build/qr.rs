@@ -0,0 +1,508 @@
+// Copyright 2020 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A small QR code encoder, to link example pages from slides and handouts without an external
+//! service.
+//!
+//! It only supports what urls need: the byte mode and the medium error correction level, which
+//! recovers from about 15% of the code being damaged, in all versions up to 40.
+
+/// The number of error correction codewords per block at the medium level, by version.
+const ECC_PER_BLOCK: [usize; 41] = [
+    0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26, 30, 22, 22, 24, 24, 28, 28, 26, 26, 26, 26, 28, 28,
+    28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28,
+];
+
+/// The number of error correction blocks at the medium level, by version.
+const BLOCKS: [usize; 41] = [
+    0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5, 5, 8, 9, 9, 10, 10, 11, 13, 14, 16, 17, 17, 18, 20, 21, 23,
+    25, 26, 28, 29, 31, 33, 35, 37, 38, 40, 43, 45, 47, 49,
+];
+
+/// The modules of a QR code, `true` being dark, by row.
+pub struct QrCode {
+    size: usize,
+    modules: Vec<Vec<bool>>,
+    /// Whether each module belongs to a function pattern, which masks leave alone.
+    function: Vec<Vec<bool>>,
+}
+
+/// Encode `data` into the smallest QR code holding it, or `None` if it is too long for any.
+pub fn encode(data: &[u8]) -> Option<QrCode> {
+    let version = (1..=40).find(|&v| {
+        let count_bits = if v < 10 { 8 } else { 16 };
+        data.len() < 1 << count_bits && 4 + count_bits + data.len() * 8 <= data_codewords(v) * 8
+    })?;
+
+    // The byte mode indicator, the length and the data, padded to the capacity.
+    let capacity = data_codewords(version) * 8;
+    let mut bits = Vec::with_capacity(capacity);
+    let mut push = |value: usize, count: usize| {
+        bits.extend((0..count).rev().map(|i| (value >> i) & 1 != 0));
+    };
+    push(0b0100, 4);
+    push(data.len(), if version < 10 { 8 } else { 16 });
+    for &b in data {
+        push(usize::from(b), 8);
+    }
+    let terminator = (capacity - bits.len()).min(4);
+    bits.extend(std::iter::repeat_n(false, terminator));
+    bits.extend(std::iter::repeat_n(false, (8 - bits.len() % 8) % 8));
+    let mut codewords: Vec<u8> = bits
+        .chunks(8)
+        .map(|byte| byte.iter().fold(0, |acc, &bit| acc << 1 | u8::from(bit)))
+        .collect();
+    for pad in [0xec, 0x11].iter().cycle() {
+        if codewords.len() == capacity / 8 {
+            break;
+        }
+        codewords.push(*pad);
+    }
+
+    let mut qr = QrCode::new(version);
+    qr.draw_codewords(&interleave(version, &codewords));
+    // Use the mask leaving the fewest patterns which confuse scanners.
+    let mask = (0..8)
+        .min_by_key(|&mask| {
+            qr.apply_mask(mask);
+            qr.draw_format_bits(mask);
+            let penalty = qr.penalty();
+            qr.apply_mask(mask);
+            penalty
+        })
+        .unwrap();
+    qr.apply_mask(mask);
+    qr.draw_format_bits(mask);
+    Some(qr)
+}
+
+impl QrCode {
+    /// An empty code of `version` with its function patterns drawn.
+    fn new(version: usize) -> QrCode {
+        let size = version * 4 + 17;
+        let mut qr = QrCode {
+            size,
+            modules: vec![vec![false; size]; size],
+            function: vec![vec![false; size]; size],
+        };
+        for i in 0..size {
+            qr.set_function(6, i, i % 2 == 0);
+            qr.set_function(i, 6, i % 2 == 0);
+        }
+        for &(x, y) in &[(3, 3), (size - 4, 3), (3, size - 4)] {
+            qr.draw_finder(x, y);
+        }
+        let positions = alignment_positions(version);
+        let last = positions.len().saturating_sub(1);
+        for (i, &x) in positions.iter().enumerate() {
+            for (j, &y) in positions.iter().enumerate() {
+                // Alignment patterns don't overlap the finders.
+                if ![(0, 0), (0, last), (last, 0)].contains(&(i, j)) {
+                    qr.draw_alignment(x, y);
+                }
+            }
+        }
+        // Reserve the format bits until the mask is known.
+        qr.draw_format_bits(0);
+        if version >= 7 {
+            let bits = version_bits(version);
+            for i in 0..18 {
+                let dark = (bits >> i) & 1 != 0;
+                let (a, b) = (size - 11 + i % 3, i / 3);
+                qr.set_function(a, b, dark);
+                qr.set_function(b, a, dark);
+            }
+        }
+        qr
+    }
+
+    /// Set the module in column `x` and row `y` as part of a function pattern.
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y][x] = dark;
+        self.function[y][x] = true;
+    }
+
+    /// Draw a finder pattern and its separator around the center `x`, `y`.
+    fn draw_finder(&mut self, x: usize, y: usize) {
+        for dy in -4i32..=4 {
+            for dx in -4i32..=4 {
+                let (xx, yy) = (x as i32 + dx, y as i32 + dy);
+                if (0..self.size as i32).contains(&xx) && (0..self.size as i32).contains(&yy) {
+                    let distance = dx.abs().max(dy.abs());
+                    self.set_function(xx as usize, yy as usize, distance != 2 && distance != 4);
+                }
+            }
+        }
+    }
+
+    fn draw_alignment(&mut self, x: usize, y: usize) {
+        for dy in -2i32..=2 {
+            for dx in -2i32..=2 {
+                let dark = dx.abs().max(dy.abs()) != 1;
+                self.set_function((x as i32 + dx) as usize, (y as i32 + dy) as usize, dark);
+            }
+        }
+    }
+
+    /// Draw both copies of the format bits for the medium level and `mask`, and the dark module.
+    fn draw_format_bits(&mut self, mask: usize) {
+        let bits = format_bits(mask);
+        let bit = |i: usize| (bits >> i) & 1 != 0;
+        let size = self.size;
+        for i in 0..6 {
+            self.set_function(8, i, bit(i));
+        }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(14 - i, 8, bit(i));
+        }
+        for i in 0..8 {
+            self.set_function(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(8, size - 15 + i, bit(i));
+        }
+        self.set_function(8, size - 8, true);
+    }
+
+    /// Fill the modules outside of the function patterns with `data`, in the zigzag order going up
+    /// and down two columns at a time from the right.
+    fn draw_codewords(&mut self, data: &[u8]) {
+        let mut i = 0;
+        let mut right = self.size - 1;
+        loop {
+            // Skip the vertical timing pattern.
+            if right == 6 {
+                right = 5;
+            }
+            let upward = (right + 1) & 2 == 0;
+            for vert in 0..self.size {
+                let y = if upward { self.size - 1 - vert } else { vert };
+                for x in [right, right - 1] {
+                    if !self.function[y][x] && i < data.len() * 8 {
+                        self.modules[y][x] = (data[i / 8] >> (7 - i % 8)) & 1 != 0;
+                        i += 1;
+                    }
+                }
+            }
+            if right < 3 {
+                return;
+            }
+            right -= 2;
+        }
+    }
+
+    /// Flip the data modules selected by `mask`. Applying the same mask again undoes it.
+    fn apply_mask(&mut self, mask: usize) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let flip = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                if flip && !self.function[y][x] {
+                    self.modules[y][x] ^= true;
+                }
+            }
+        }
+    }
+
+    /// The penalty score of the code as masked, following the rules of the standard.
+    fn penalty(&self) -> usize {
+        let size = self.size;
+        let column = |x: usize| -> Vec<bool> { (0..size).map(|y| self.modules[y][x]).collect() };
+        let lines: Vec<Vec<bool>> = self
+            .modules
+            .iter()
+            .cloned()
+            .chain((0..size).map(column))
+            .collect();
+        let finder_like = [
+            [
+                true, false, true, true, true, false, true, false, false, false, false,
+            ],
+            [
+                false, false, false, false, true, false, true, true, true, false, true,
+            ],
+        ];
+
+        let mut penalty = 0;
+        for line in &lines {
+            // Runs of five or more modules of the same color.
+            let mut run = 1;
+            for i in 1..=size {
+                if i < size && line[i] == line[i - 1] {
+                    run += 1;
+                } else {
+                    if run >= 5 {
+                        penalty += run - 2;
+                    }
+                    run = 1;
+                }
+            }
+            // Patterns looking like a finder.
+            penalty += 40
+                * line
+                    .windows(11)
+                    .filter(|w| finder_like.iter().any(|p| w == p))
+                    .count();
+        }
+        // Blocks of two by two modules of the same color.
+        for y in 1..size {
+            for x in 1..size {
+                let color = self.modules[y][x];
+                if [(x - 1, y), (x, y - 1), (x - 1, y - 1)]
+                    .iter()
+                    .all(|&(xx, yy)| self.modules[yy][xx] == color)
+                {
+                    penalty += 3;
+                }
+            }
+        }
+        // An imbalance of dark and light modules.
+        let dark = self.modules.iter().flatten().filter(|&&m| m).count();
+        let total = size * size;
+        let deviation = (dark * 20).abs_diff(total * 10) / total;
+        penalty + deviation * 10
+    }
+
+    /// Render the code as an svg image, with the quiet zone the standard requires around it.
+    pub fn svg(&self) -> String {
+        let mut path = String::new();
+        for (y, row) in self.modules.iter().enumerate() {
+            for (x, _) in row.iter().enumerate().filter(|(_, &dark)| dark) {
+                path.push_str(&format!("M{},{}h1v1h-1z", x + 4, y + 4));
+            }
+        }
+        format!(
+            concat!(
+                "<svg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 {size} {size}' ",
+                "shape-rendering='crispEdges'><rect width='{size}' height='{size}' fill='#fff'/>",
+                "<path d='{path}' fill='#000'/></svg>"
+            ),
+            size = self.size + 8,
+            path = path
+        )
+    }
+}
+
+/// The number of modules of `version` left for data and error correction codewords.
+fn raw_modules(version: usize) -> usize {
+    let mut modules = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let alignments = version / 7 + 2;
+        modules -= (25 * alignments - 10) * alignments - 55;
+        if version >= 7 {
+            modules -= 36;
+        }
+    }
+    modules
+}
+
+/// The number of data codewords `version` holds at the medium level.
+fn data_codewords(version: usize) -> usize {
+    raw_modules(version) / 8 - ECC_PER_BLOCK[version] * BLOCKS[version]
+}
+
+/// The centers of the alignment patterns of `version`, in both directions.
+fn alignment_positions(version: usize) -> Vec<usize> {
+    if version == 1 {
+        return Vec::new();
+    }
+    let count = version / 7 + 2;
+    let step = (version * 8 + count * 3 + 5) / (count * 4 - 4) * 2;
+    let size = version * 4 + 17;
+    let mut positions: Vec<usize> = (0..count - 1).map(|i| size - 7 - i * step).collect();
+    positions.push(6);
+    positions.reverse();
+    positions
+}
+
+/// The 15 format bits of the medium level with `mask`, with their error correction.
+fn format_bits(mask: usize) -> usize {
+    // The medium level is encoded as 0.
+    let data = mask;
+    let mut rem = data;
+    for _ in 0..10 {
+        rem = (rem << 1) ^ ((rem >> 9) * 0x537);
+    }
+    ((data << 10) | rem) ^ 0x5412
+}
+
+/// The 18 version bits of `version`, with their error correction.
+fn version_bits(version: usize) -> usize {
+    let mut rem = version;
+    for _ in 0..12 {
+        rem = (rem << 1) ^ ((rem >> 11) * 0x1f25);
+    }
+    (version << 12) | rem
+}
+
+/// Split `data` into the blocks of `version`, add their error correction codewords, and
+/// interleave them.
+fn interleave(version: usize, data: &[u8]) -> Vec<u8> {
+    let blocks = BLOCKS[version];
+    let ecc_len = ECC_PER_BLOCK[version];
+    let raw = raw_modules(version) / 8;
+    // The first blocks are one codeword shorter than the others.
+    let short_blocks = blocks - raw % blocks;
+    let short_len = raw / blocks - ecc_len;
+    let divisor = rs_divisor(ecc_len);
+
+    let mut rest = data;
+    let split: Vec<(&[u8], Vec<u8>)> = (0..blocks)
+        .map(|i| {
+            let (block, tail) = rest.split_at(short_len + usize::from(i >= short_blocks));
+            rest = tail;
+            (block, rs_remainder(block, &divisor))
+        })
+        .collect();
+
+    let mut out = Vec::with_capacity(raw);
+    for i in 0..=short_len {
+        out.extend(split.iter().filter_map(|(block, _)| block.get(i)));
+    }
+    for i in 0..ecc_len {
+        out.extend(split.iter().map(|(_, ecc)| ecc[i]));
+    }
+    out
+}
+
+/// Multiply in the Galois field of 256 elements used by QR codes.
+fn gf_mul(x: u8, y: u8) -> u8 {
+    let mut z: u32 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x11d);
+        z ^= u32::from((y >> i) & 1) * u32::from(x);
+    }
+    z as u8
+}
+
+/// The Reed-Solomon generator polynomial of `degree`, without its leading coefficient.
+fn rs_divisor(degree: usize) -> Vec<u8> {
+    let mut divisor = vec![0; degree];
+    divisor[degree - 1] = 1;
+    let mut root = 1;
+    for _ in 0..degree {
+        for j in 0..degree {
+            divisor[j] = gf_mul(divisor[j], root);
+            if j + 1 < degree {
+                divisor[j] ^= divisor[j + 1];
+            }
+        }
+        root = gf_mul(root, 2);
+    }
+    divisor
+}
+
+/// The error correction codewords of `data`.
+fn rs_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut remainder = vec![0; divisor.len()];
+    for &b in data {
+        let factor = b ^ remainder.remove(0);
+        remainder.push(0);
+        for (r, &d) in remainder.iter_mut().zip(divisor) {
+            *r ^= gf_mul(d, factor);
+        }
+    }
+    remainder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_codes() {
+        // The example of the standard, encoding "01234567" as a version 1 code at the medium level.
+        let data = [
+            0x10, 0x20, 0x0c, 0x56, 0x61, 0x80, 0xec, 0x11, 0xec, 0x11, 0xec, 0x11, 0xec, 0x11,
+            0xec, 0x11,
+        ];
+        assert_eq!(
+            rs_remainder(&data, &rs_divisor(10)),
+            [0xa5, 0x24, 0xd4, 0xc1, 0xed, 0x36, 0xc7, 0x87, 0x2c, 0x55]
+        );
+        assert_eq!(format_bits(0), 0b101010000010010);
+        assert_eq!(version_bits(7), 0b000111110010010100);
+        assert_eq!(alignment_positions(32), [6, 34, 60, 86, 112, 138]);
+
+        // 40 bytes take a version 3 code, holding up to 42.
+        let qr = encode(b"https://example.org/druid/html/calc.html").unwrap();
+        assert_eq!(qr.size, 29);
+        assert!(encode(&[b'a'; 2331]).is_some());
+        assert!(encode(&[b'a'; 2332]).is_none());
+    }
+
+    #[test]
+    fn fills_each_version_to_its_capacity() {
+        // The byte mode capacities at the medium level, from table 7 of ISO/IEC 18004.
+        let capacities = [
+            14, 26, 42, 62, 84, 106, 122, 152, 180, 213, 251, 287, 331, 362, 412, 450, 504, 560,
+            624, 666, 711, 779, 857, 911, 997, 1059, 1125, 1190, 1264, 1370, 1452, 1538, 1628,
+            1722, 1809, 1911, 1989, 2099, 2213, 2331,
+        ];
+        for (version, &capacity) in (1..).zip(&capacities) {
+            let size = version * 4 + 17;
+            assert_eq!(encode(&vec![b'a'; capacity]).unwrap().size, size);
+            if version < 40 {
+                // From version 10 on, the length takes 16 bits instead of 8.
+                let next = encode(&vec![b'a'; capacity + 1]).unwrap();
+                assert_eq!(next.size, size + 4, "{} bytes", capacity + 1);
+            }
+        }
+        assert_eq!(encode(b"").unwrap().size, 21);
+    }
+
+    #[test]
+    fn encodes_format_and_version_information() {
+        // Table C.1 of ISO/IEC 18004, at the medium level.
+        let formats = [
+            0b101010000010010,
+            0b101000100100101,
+            0b101111001111100,
+            0b101101101001011,
+            0b100010111111001,
+            0b100000011001110,
+            0b100111110010111,
+            0b100101010100000,
+        ];
+        for (mask, &bits) in formats.iter().enumerate() {
+            assert_eq!(format_bits(mask), bits, "mask {}", mask);
+        }
+        // Table D.1.
+        assert_eq!(version_bits(8), 0b001000010110111100);
+        assert_eq!(version_bits(21), 0b010101011010000011);
+        assert_eq!(version_bits(40), 0b101000110001101001);
+        assert_eq!(alignment_positions(1), Vec::<usize>::new());
+        assert_eq!(alignment_positions(2), [6, 18]);
+        assert_eq!(alignment_positions(7), [6, 22, 38]);
+        assert_eq!(alignment_positions(40), [6, 30, 58, 86, 114, 142, 170]);
+        // The data codewords plus the error correction codewords fill the modules left over.
+        for version in 1..=40 {
+            let ecc = ECC_PER_BLOCK[version] * BLOCKS[version];
+            assert_eq!(data_codewords(version) + ecc, raw_modules(version) / 8);
+        }
+        assert_eq!(data_codewords(1), 16);
+        assert_eq!(data_codewords(40), 2334);
+    }
+}
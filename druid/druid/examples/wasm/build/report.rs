@@ -0,0 +1,80 @@
+// Copyright 2020 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The build report, recording how long each phase of the generation took, to track down slow
+//! builds. See the README for its format.
+
+use std::time::{Duration, Instant};
+
+use crate::json::Json;
+
+/// The version of the format of the report, incremented on incompatible changes.
+const FORMAT_VERSION: i64 = 1;
+
+/// Measures the phases of the build, one after the other.
+pub struct Report {
+    start: Instant,
+    /// When the current phase started.
+    phase_start: Instant,
+    phases: Vec<(&'static str, Duration)>,
+}
+
+impl Report {
+    /// Start measuring the first phase.
+    pub fn start() -> Report {
+        let now = Instant::now();
+        Report {
+            start: now,
+            phase_start: now,
+            phases: Vec::new(),
+        }
+    }
+
+    /// End the current phase, called `name`, and start the next one.
+    pub fn phase(&mut self, name: &'static str) {
+        let now = Instant::now();
+        self.phases.push((name, now - self.phase_start));
+        self.phase_start = now;
+    }
+
+    /// The report of the phases so far, along with the given `counts`, and the `sizes` of the pages
+    /// and the bundle if they were measured.
+    pub fn json(&self, counts: Vec<(&str, usize)>, sizes: Option<Json>) -> Json {
+        let micros = |d: Duration| Json::Number(d.as_micros() as i64);
+        let phases = self
+            .phases
+            .iter()
+            .map(|(name, duration)| {
+                Json::object(vec![
+                    ("name", (*name).into()),
+                    ("micros", micros(*duration)),
+                ])
+            })
+            .collect();
+        let counts = counts
+            .into_iter()
+            .map(|(name, count)| (name, Json::Number(count as i64)));
+
+        let mut report = vec![
+            ("version", Json::Number(FORMAT_VERSION)),
+            ("micros", micros(self.phase_start - self.start)),
+            ("phases", Json::Array(phases)),
+            ("counts", Json::object(counts)),
+        ];
+        if let Some(sizes) = sizes {
+            report.push(("sizes", sizes));
+        }
+        Json::object(report)
+    }
+}
@@ -0,0 +1,231 @@
+// Copyright 2020 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Configuration snippets for serving the examples with a web server.
+//!
+//! Browsers only compile WASM streamed with the `application/wasm` type, and the
+//! `Cross-Origin-Opener-Policy`/`Cross-Origin-Embedder-Policy` headers are needed for features such
+//! as `SharedArrayBuffer`. The generated snippets set both for the generated site, and only serve
+//! its files and the bundle, not the sources of the crate they are generated in.
+
+use std::collections::BTreeSet;
+use std::path::{Component, Path, PathBuf};
+
+use crate::config::Config;
+
+/// The headers sent with every file. The files are not fingerprinted, so they have to be
+/// revalidated to pick up rebuilds.
+const HEADERS: &[(&str, &str)] = &[
+    ("Cross-Origin-Opener-Policy", "same-origin"),
+    ("Cross-Origin-Embedder-Policy", "require-corp"),
+    ("Cache-Control", "no-cache"),
+];
+
+/// A web server to generate a configuration snippet for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Server {
+    Caddy,
+    Nginx,
+}
+
+impl Server {
+    /// Parse the name of a server, as given in `RCCHAT_SERVER_CONFIG`.
+    pub fn from_name(name: &str) -> Option<Server> {
+        match name {
+            "caddy" => Some(Server::Caddy),
+            "nginx" => Some(Server::Nginx),
+            _ => None,
+        }
+    }
+
+    /// The name of the snippet file, written next to the index.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Server::Caddy => "Caddyfile",
+            Server::Nginx => "nginx.conf",
+        }
+    }
+
+    /// Render the snippet serving the entries `served` of `root`, as listed by [`served`].
+    pub fn snippet(self, root: &Path, served: &[String], config: &Config) -> String {
+        let root = root.display();
+        let index = format!("index.{}", config.markup.extension());
+        let mut out =
+            String::from("# This file is automatically generated and must not be committed.\n");
+        match self {
+            Server::Caddy => {
+                out.push_str("# Serves the druid WASM examples on port 8080: `caddy run`.\n");
+                out.push_str(":8080 {\n");
+                out.push_str(&format!("\troot * \"{}\"\n", root));
+                out.push_str("\t@hidden not path /");
+                for entry in served {
+                    let wildcard = if entry.ends_with('/') { "*" } else { "" };
+                    out.push_str(&format!(" \"/{}{}\"", entry, wildcard));
+                }
+                out.push_str("\n\trespond @hidden 404\n");
+                out.push_str(&format!("\ttry_files {{path}} {{path}}/{}\n", index));
+                if config.gzip {
+                    out.push_str("\tfile_server {\n\t\tprecompressed gzip\n\t}\n");
+                } else {
+                    out.push_str("\tfile_server\n");
+                }
+                out.push_str("\theader {\n");
+                for (name, value) in HEADERS {
+                    out.push_str(&format!("\t\t{} {}\n", name, value));
+                }
+                out.push_str("\t}\n");
+                out.push_str("\t@wasm path *.wasm\n");
+                out.push_str("\theader @wasm Content-Type application/wasm\n");
+                out.push_str("}\n");
+            }
+            Server::Nginx => {
+                let headers = |indent: &str| -> String {
+                    HEADERS
+                        .iter()
+                        .map(|(name, value)| {
+                            format!("{}add_header {} {} always;\n", indent, name, value)
+                        })
+                        .collect()
+                };
+                out.push_str("# Serves the druid WASM examples, include it in a `server` block.\n");
+                out.push_str("location / {\n");
+                out.push_str(&format!("    root \"{}\";\n", root));
+                out.push_str(&format!("    index {};\n", index));
+                if config.gzip {
+                    out.push_str("    gzip_static on;\n");
+                }
+                out.push_str(&headers("    "));
+                out.push('\n');
+                // Everything but the served entries is hidden, before the `.wasm` files match.
+                let allowed: Vec<String> = served
+                    .iter()
+                    .map(|entry| match entry.strip_suffix('/') {
+                        Some(dir) => format!("{}/", regex_escape(dir)),
+                        None => format!("{}$", regex_escape(entry)),
+                    })
+                    .collect();
+                out.push_str(&format!(
+                    "    location ~ \"^/(?!(?:${}))\" {{\n",
+                    allowed
+                        .iter()
+                        .map(|a| format!("|{}", a))
+                        .collect::<String>()
+                ));
+                out.push_str("        return 404;\n");
+                out.push_str("    }\n");
+                // Headers are not inherited by nested locations which add their own.
+                out.push_str("    location ~ \\.wasm$ {\n");
+                out.push_str("        types { }\n");
+                out.push_str("        default_type application/wasm;\n");
+                out.push_str(&headers("        "));
+                out.push_str("    }\n");
+                out.push_str("}\n");
+            }
+        }
+        out
+    }
+}
+
+/// The directory to serve: `site_dir`, or the nearest directory containing both it and the local
+/// bundle in `pkg_dir`.
+pub fn root(site_dir: &Path, pkg_dir: Option<&Path>) -> PathBuf {
+    let mut root = site_dir.to_path_buf();
+    if let Some(pkg_dir) = pkg_dir {
+        while !pkg_dir.starts_with(&root) && root.pop() {}
+    }
+    root
+}
+
+/// The entries of `root` to serve: those containing any of the generated `files` or the local
+/// bundle in `pkg_dir`, with a trailing `/` for directories. Dotfiles are never served.
+pub fn served<'a>(
+    root: &Path,
+    files: impl Iterator<Item = &'a Path>,
+    pkg_dir: Option<&Path>,
+) -> Vec<String> {
+    let pkg = pkg_dir.map(|pkg_dir| pkg_dir.join("druid_wasm_examples.js"));
+    let mut entries = BTreeSet::new();
+    for path in files.map(Path::to_path_buf).chain(pkg) {
+        let mut components = match path.strip_prefix(root) {
+            Ok(relative) => relative.components(),
+            Err(_) => continue,
+        };
+        let name = match components.next() {
+            Some(Component::Normal(name)) => name.to_string_lossy(),
+            _ => continue,
+        };
+        if name.starts_with('.') {
+            continue;
+        }
+        let slash = if components.next().is_some() { "/" } else { "" };
+        entries.insert(format!("{}{}", name, slash));
+    }
+    entries.into_iter().collect()
+}
+
+/// Escape the characters of `s` which are special in the regular expressions of nginx.
+fn regex_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if "\\.^$*+?()[]{}|\"".contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serves_only_the_site() {
+        let crate_dir = Path::new("/srv/wasm");
+        let site_dir = crate_dir.join("docs");
+        let pkg_dir = crate_dir.join("pkg");
+        assert_eq!(root(crate_dir, Some(&pkg_dir)), crate_dir);
+        assert_eq!(root(&site_dir, Some(&pkg_dir)), crate_dir);
+        assert_eq!(root(&site_dir, Some(&site_dir.join("pkg"))), site_dir);
+        assert_eq!(root(&site_dir, None), site_dir);
+
+        let files = [
+            crate_dir.join("index.html"),
+            crate_dir.join("html/calc.html"),
+            crate_dir.join("html/calc.html.gz"),
+            crate_dir.join("robots.txt"),
+            crate_dir.join(".nojekyll"),
+            Path::new("/elsewhere/index.html").to_path_buf(),
+        ];
+        let served = served(
+            crate_dir,
+            files.iter().map(PathBuf::as_path),
+            Some(&pkg_dir),
+        );
+        assert_eq!(served, ["html/", "index.html", "pkg/", "robots.txt"]);
+
+        let config = Config::from_env(crate_dir);
+        let caddy = Server::Caddy.snippet(crate_dir, &served, &config);
+        assert!(caddy.contains(concat!(
+            "\t@hidden not path / \"/html/*\" \"/index.html\" \"/pkg/*\" \"/robots.txt\"\n",
+            "\trespond @hidden 404\n",
+        )));
+        let nginx = Server::Nginx.snippet(crate_dir, &served, &config);
+        assert!(nginx.contains(concat!(
+            "    location ~ \"^/(?!(?:$|html/|index\\.html$|pkg/|robots\\.txt$))\" {\n",
+            "        return 404;\n",
+        )));
+        assert!(nginx.find("return 404").unwrap() < nginx.find("application/wasm").unwrap());
+    }
+}
@@ -0,0 +1,49 @@
+// Copyright 2020 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The spec of the runtime test of the pages, for a headless browser to check that each example
+//! actually initializes. See the README for its format.
+
+use crate::config::Config;
+use crate::discover::Example;
+use crate::json::Json;
+use crate::meta::Status;
+
+/// The version of the format of the spec, incremented on incompatible changes.
+const FORMAT_VERSION: i64 = 1;
+
+/// The spec listing the page of each of `examples`, with the entry function it has to run.
+pub fn test_spec(examples: &[Example], config: &Config) -> Json {
+    let ext = config.markup.extension();
+    let mut sorted: Vec<&Example> = examples.iter().collect();
+    config.sort.sort(&mut sorted);
+    let pages = sorted
+        .into_iter()
+        .map(|e| {
+            Json::object(vec![
+                ("module", e.module_path().into()),
+                ("url", config.page_url(&e.page).into()),
+                ("entry", e.entry.as_str().into()),
+                ("status", Json::opt_string(e.meta.status.map(Status::name))),
+            ])
+        })
+        .collect();
+
+    Json::object(vec![
+        ("version", Json::Number(FORMAT_VERSION)),
+        ("base_url", Json::opt_string(config.base_url.as_deref())),
+        ("index", format!("index.{}", ext).into()),
+        ("pages", Json::Array(pages)),
+    ])
+}
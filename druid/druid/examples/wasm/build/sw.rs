@@ -0,0 +1,153 @@
+// Copyright 2020 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The service worker caching the examples for offline viewing.
+
+use crate::config::Config;
+use crate::discover::Example;
+use crate::json::Json;
+
+/// The prefix of the names of the caches, followed by a stamp identifying their contents.
+const CACHE_PREFIX: &str = "druid-wasm-examples-";
+
+/// Render `sw.js`, which precaches the `index_files` written, the example pages and the wasm
+/// bundle, and serves them from the cache.
+///
+/// The cache is named after the crate `version` and the `fingerprint` of the generated files, so
+/// that a changed worker replaces the cache of the previous one.
+pub fn service_worker(
+    index_files: &[String],
+    examples: &[Example],
+    config: &Config,
+    version: &str,
+    fingerprint: u64,
+) -> String {
+    let mut pages = vec![Json::from("./")];
+    for file in index_files {
+        pages.push(format!("./{}", file).into());
+    }
+    if config.embed_source {
+        pages.push("./style.css".into());
+    }
+    if config.manifest {
+        pages.push("./manifest.webmanifest".into());
+        for icon in &config.icons {
+            pages.push(format!("./{}", icon.file).into());
+        }
+    }
+    for example in examples {
+        pages.push(format!("./{}", config.page_url(&example.page)).into());
+    }
+    let bundle = Json::Array(vec![
+        format!("{}/druid_wasm_examples.js", config.pkg_base).into(),
+        format!("{}/druid_wasm_examples_bg.wasm", config.pkg_base).into(),
+    ]);
+
+    format!(
+        r#"// This file is automatically generated and must not be committed.
+
+const CACHE = {cache};
+// Relative to the directory of the index.
+const PAGES = {pages};
+// Relative to the example pages, as imported by them.
+const BUNDLE = {bundle};
+
+self.addEventListener('install', event => {{
+    const scope = self.registration.scope;
+    const html = new URL('{pages_dir}', scope);
+    const urls = PAGES.map(url => new URL(url, scope).href)
+        .concat(BUNDLE.map(url => new URL(url, html).href));
+    event.waitUntil(
+        caches.open(CACHE)
+            .then(cache => cache.addAll(urls))
+            .then(() => self.skipWaiting())
+    );
+}});
+
+// Drop the caches of previous versions.
+self.addEventListener('activate', event => {{
+    event.waitUntil(
+        caches.keys()
+            .then(keys => Promise.all(keys
+                .filter(key => key.startsWith({prefix}) && key !== CACHE)
+                .map(key => caches.delete(key))))
+            .then(() => self.clients.claim())
+    );
+}});
+
+self.addEventListener('fetch', event => {{
+    if (event.request.method !== 'GET') {{
+        return;
+    }}
+    event.respondWith(
+        caches.match(event.request).then(cached => cached || fetch(event.request))
+    );
+}});
+"#,
+        cache = Json::from(format!("{}{}-{:016x}", CACHE_PREFIX, version, fingerprint)),
+        pages = Json::Array(pages),
+        bundle = bundle,
+        pages_dir = if config.flat_pages { "./" } else { "html/" },
+        prefix = Json::from(CACHE_PREFIX),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discover::{discover, Root};
+    use crate::html::index_pages;
+    use std::fs;
+
+    #[test]
+    fn precaches_only_written_files() {
+        let dir = crate::fixture_dir("sw_unlisted");
+        let examples_dir = dir.join("examples");
+        fs::create_dir_all(&examples_dir).unwrap();
+        for name in &["calc", "hello", "list"] {
+            fs::write(examples_dir.join(format!("{}.rs", name)), "").unwrap();
+        }
+        fs::write(examples_dir.join("list.toml"), "unlisted = true").unwrap();
+        let roots = [Root {
+            name: None,
+            dir: examples_dir,
+        }];
+        let examples = discover(&roots, "_", false).unwrap().examples;
+
+        // Two listed examples fit on one index page, even though there are three examples.
+        let mut config = Config::from_env(&dir);
+        config.index_page_size = Some(2);
+        let site_dir = dir.join("site");
+        fs::create_dir_all(site_dir.join("html")).unwrap();
+        let mut index_files = Vec::new();
+        for (file, page) in index_pages(&examples, &[], &config) {
+            fs::write(site_dir.join(&file), page).unwrap();
+            index_files.push(file);
+        }
+        for example in &examples {
+            fs::write(site_dir.join(config.page_url(&example.page)), "").unwrap();
+        }
+
+        let worker = service_worker(&index_files, &examples, &config, "0.1.0", 0);
+        let pages = worker
+            .lines()
+            .find_map(|line| line.strip_prefix("const PAGES = "))
+            .unwrap();
+        let urls: Vec<&str> = pages.split('"').skip(1).step_by(2).collect();
+        assert_eq!(urls.len(), 5, "{}", pages);
+        for url in urls {
+            assert!(site_dir.join(url).exists(), "{} was never written", url);
+        }
+    }
+}
@@ -0,0 +1,188 @@
+// Copyright 2020 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A minimal zip archive writer, to package the generated site without pulling in dependencies.
+//!
+//! Entries are compressed with deflate when that makes them smaller, and stored otherwise. All
+//! entries carry the same fixed modification time, so the archive only depends on its contents.
+
+use std::convert::TryFrom;
+
+use crate::compress::{crc32, deflate};
+
+/// The DOS date of the modification time of all entries, 1980-01-01, the earliest there is.
+const DATE: u16 = 1 << 5 | 1;
+/// The version of the format needed to extract the entries, 2.0 for deflate.
+const VERSION: u16 = 20;
+/// The flag telling that the names are encoded in UTF-8.
+const UTF8: u16 = 1 << 11;
+
+/// Archive `files`, given by their path inside the archive, with `/` separators, and contents.
+///
+/// Panics if the archive would need the zip64 extensions, with more than 65535 entries or 4 GiB.
+pub fn zip(files: &[(String, Vec<u8>)]) -> Vec<u8> {
+    assert!(
+        files.len() <= usize::from(u16::MAX),
+        "too many files for a zip archive"
+    );
+    let mut out = Vec::new();
+    let mut central = Vec::new();
+    for (name, contents) in files {
+        let compressed = deflate(contents);
+        let (method, data) = if compressed.len() < contents.len() {
+            (8u16, compressed.as_slice())
+        } else {
+            (0u16, contents.as_slice())
+        };
+        let offset = size(out.len());
+        // The fields shared by the local header and the central directory entry.
+        let mut common = Vec::new();
+        push16(&mut common, VERSION);
+        push16(&mut common, UTF8);
+        push16(&mut common, method);
+        push16(&mut common, 0);
+        push16(&mut common, DATE);
+        push32(&mut common, crc32(contents));
+        push32(&mut common, size(data.len()));
+        push32(&mut common, size(contents.len()));
+        push16(&mut common, name.len() as u16);
+        push16(&mut common, 0);
+
+        push32(&mut out, 0x0403_4b50);
+        out.extend(&common);
+        out.extend(name.as_bytes());
+        out.extend(data);
+
+        push32(&mut central, 0x0201_4b50);
+        push16(&mut central, VERSION);
+        central.extend(&common);
+        // No comment, on the first disk, without attributes.
+        push16(&mut central, 0);
+        push16(&mut central, 0);
+        push16(&mut central, 0);
+        push32(&mut central, 0);
+        push32(&mut central, offset);
+        central.extend(name.as_bytes());
+    }
+
+    let offset = size(out.len());
+    out.extend(&central);
+    push32(&mut out, 0x0605_4b50);
+    push16(&mut out, 0);
+    push16(&mut out, 0);
+    push16(&mut out, files.len() as u16);
+    push16(&mut out, files.len() as u16);
+    push32(&mut out, size(central.len()));
+    push32(&mut out, offset);
+    push16(&mut out, 0);
+    out
+}
+
+fn size(len: usize) -> u32 {
+    u32::try_from(len).expect("the zip archive is too large")
+}
+
+fn push16(out: &mut Vec<u8>, value: u16) {
+    out.extend(&value.to_le_bytes());
+}
+
+fn push32(out: &mut Vec<u8>, value: u32) {
+    out.extend(&value.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read16(bytes: &[u8], at: usize) -> usize {
+        usize::from(u16::from_le_bytes([bytes[at], bytes[at + 1]]))
+    }
+
+    fn read32(bytes: &[u8], at: usize) -> usize {
+        u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]) as usize
+    }
+
+    #[test]
+    fn archives_files() {
+        let page = "<p>hello</p>".repeat(20).into_bytes();
+        let files = vec![
+            ("index.html".to_string(), page.clone()),
+            ("html/a.png".to_string(), vec![7, 1, 9]),
+        ];
+        let archive = zip(&files);
+
+        let end = archive.len() - 22;
+        assert_eq!(read32(&archive, end), 0x0605_4b50);
+        assert_eq!(read16(&archive, end + 10), 2);
+        let mut entry = read32(&archive, end + 16);
+        for ((name, contents), method) in files.iter().zip(&[8, 0]) {
+            assert_eq!(read32(&archive, entry), 0x0201_4b50);
+            assert_eq!(read16(&archive, entry + 10), *method);
+            assert_eq!(read32(&archive, entry + 16), crc32(contents) as usize);
+            assert_eq!(read32(&archive, entry + 24), contents.len());
+            let name_len = read16(&archive, entry + 28);
+            assert_eq!(&archive[entry + 46..entry + 46 + name_len], name.as_bytes());
+
+            // The local header repeats the entry.
+            let local = read32(&archive, entry + 42);
+            assert_eq!(read32(&archive, local), 0x0403_4b50);
+            assert_eq!(
+                &archive[local + 4..local + 30],
+                &archive[entry + 6..entry + 32]
+            );
+            entry += 46 + name_len;
+        }
+        assert_eq!(entry, end);
+        // Compressible pages shrink.
+        assert!(read32(&archive, read32(&archive, end + 16) + 20) < page.len());
+    }
+
+    #[test]
+    fn archives_edge_cases() {
+        // An empty archive is only the end of the central directory.
+        let mut empty = vec![0x50, 0x4b, 0x05, 0x06];
+        empty.extend(&[0; 18]);
+        assert_eq!(zip(&[]), empty);
+
+        let large: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+        let files = vec![
+            ("empty.txt".to_string(), Vec::new()),
+            ("html/übersicht.html".to_string(), large.clone()),
+        ];
+        let archive = zip(&files);
+        let end = archive.len() - 22;
+        assert_eq!(read16(&archive, end + 8), 2);
+        assert_eq!(read32(&archive, end + 12) + read32(&archive, end + 16), end);
+
+        // Empty files are stored, without any data.
+        let entry = read32(&archive, end + 16);
+        assert_eq!(read16(&archive, entry + 10), 0);
+        assert_eq!(read32(&archive, entry + 16), 0);
+        assert_eq!(read32(&archive, entry + 20), 0);
+        assert_eq!(read16(&archive, entry + 12), 0);
+        assert_eq!(read16(&archive, entry + 14), usize::from(DATE));
+
+        // Names are flagged as UTF-8, and the deflated data inflates back to the contents.
+        let entry = entry + 46 + "empty.txt".len();
+        assert_eq!(read16(&archive, entry + 8), usize::from(UTF8));
+        let name_len = read16(&archive, entry + 28);
+        assert_eq!(name_len, "html/übersicht.html".len());
+        assert_eq!(read16(&archive, entry + 10), 8);
+        let local = read32(&archive, entry + 42);
+        let start = local + 30 + name_len;
+        let data = &archive[start..start + read32(&archive, entry + 20)];
+        assert_eq!(crate::compress::tests::inflate(data), large);
+        assert_eq!(start + data.len(), read32(&archive, end + 16));
+    }
+}